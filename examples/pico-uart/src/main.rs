@@ -17,12 +17,15 @@ use embassy_rp::uart::{BufferedUart, BufferedUartRx, BufferedUartTx, Config, Par
 use {defmt_rtt as _, panic_probe as _};
 
 use atat::AtatIngress;
-use atat::{asynch::Client, Buffers, Ingress};
+use atat::{asynch::Client, Buffers, Ingress, UrcSubscription};
 use atat::helpers::LossyStr;
-use embassy_time::{Duration, Timer};
+use embassy_time::{with_timeout, Duration};
 use embedded_alloc::Heap;
-use moko_mkl62ba_at_commands::digester::MokoDigester;
-use moko_mkl62ba_at_commands::lora::types::{LoraJoinMode, LoraJoiningStatus, LoraRegion, LoraClass};
+use moko_mkl62ba_at_commands::digester::{DigesterState, MokoDigester};
+use moko_mkl62ba_at_commands::lora::config::LoraConfig;
+use moko_mkl62ba_at_commands::lora::types::{
+    JoinOutcome, JoinPolicy, LoraClass, LoraRegion, OtaaCredentials,
+};
 use moko_mkl62ba_at_commands::urc::URCMessages;
 use moko_mkl62ba_at_commands::client::asynch::MokoMkl62BaClient;
 use moko_mkl62ba_at_commands::lora::responses::LoraReceivedBytes;
@@ -36,12 +39,12 @@ const RX_SIZE: usize = 1044;
 
 // Constants derived from TX_SIZE and RX_SIZE
 const INGRESS_BUF_SIZE: usize = RX_SIZE;
-const URC_SUBSCRIBERS: usize = 0;
-const URC_CAPACITY: usize = RX_SIZE * 3;
+const URC_SUBSCRIBERS: usize = 1;
+const URC_CAPACITY: usize = 4;
 
 type AtIngress<'a> = Ingress<
     'a,
-    MokoDigester,
+    MokoDigester<'a>,
     URCMessages,
     INGRESS_BUF_SIZE,
     URC_CAPACITY,
@@ -50,6 +53,11 @@ type AtIngress<'a> = Ingress<
 
 type AtMokoClient<'a> = Client<'a, BufferedUartTx<'a, UART1>, INGRESS_BUF_SIZE>;
 
+/// Shared by the digester in the read task and the client
+static DIGESTER: DigesterState = DigesterState::new();
+
+type AtUrcSubscription<'a> = UrcSubscription<'a, URCMessages, URC_CAPACITY, URC_SUBSCRIBERS>;
+
 macro_rules! singleton {
     ($val:expr) => {{
         type T = impl Sized;
@@ -81,7 +89,7 @@ async fn main(spawner: Spawner) {
         .cmd_cooldown(Duration::from_millis(200))
         .tx_timeout(Duration::from_millis(2000));
 
-    let digester = MokoDigester::default();
+    let digester = MokoDigester::new(&DIGESTER);
     static BUFFERS: Buffers<
         URCMessages,
         INGRESS_BUF_SIZE,
@@ -94,9 +102,10 @@ async fn main(spawner: Spawner) {
         URC_SUBSCRIBERS,
     >::new();
     let (ingress, client) = BUFFERS.split(tx, digester, config);
+    let urc_subscription = singleton!(unwrap!(BUFFERS.urc_channel.subscribe().ok()));
 
     unwrap!(spawner.spawn(read_task(ingress, rx)));
-    unwrap!(spawner.spawn(client_task(client)));
+    unwrap!(spawner.spawn(client_task(client, urc_subscription)));
 }
 
 #[embassy_executor::task]
@@ -105,122 +114,63 @@ async fn read_task(mut ingress: AtIngress<'static>, mut rx: BufferedUartRx<'stat
 }
 
 #[embassy_executor::task]
-async fn client_task(client: AtMokoClient<'static>) {
-    let client = MokoMkl62BaClient::new(client).await;
+async fn client_task(
+    client: AtMokoClient<'static>,
+    urc_subscription: &'static mut AtUrcSubscription<'static>,
+) {
+    let client = MokoMkl62BaClient::new(client, &DIGESTER).await;
     if let Err(e) = client {
         error!("Error creating client");
         return;
     }
     let mut client = client.unwrap();
-    if let Err(e) = client.join_mode_set(LoraJoinMode::Otaa).await {
-        error!("Error setting join mode");
-    } else {
-        info!("Join mode set to OTAA");
-    }
-
-    if let Err(e) = client.dev_eui_set(DEV_EUI).await {
-        error!("Error setting dev eui");
-    } else {
-        info!("Dev EUI set");
-    }
-
-    if let Err(e) = client.app_eui_set(0x0).await {
-        error!("Error setting app eui");
-    } else {
-        info!("App EUI set");
-    }
-
-    if let Err(e) = client
-        .app_key_set(APP_KEY)
-        .await
-    {
-        error!("Error setting app key");
-    } else {
-        info!("App key set");
-    }
-
-    if let Err(e) = client.lora_region_set(LoraRegion::Eu868).await {
-        error!("Error setting lora region");
-    } else {
-        info!("Lora region set");
-    }
-
-    if let Err(e) = client.lora_class_set(LoraClass::ClassC).await {
-        error!("Error setting lora class");
-    } else {
-        info!("Lora class set to Class C");
-    }
-
-    if let Err(e) = client.adr_set(false).await {
-        error!("Error setting lora adr");
-    } else {
-        info!("Lora adr set to false");
-    }
-
-    if let Err(e) = client.dr_set(5).await {
-        error!("Error setting lora dr");
-    } else {
-        info!("Lora dr set to 5");
-    }
-
-    if let Err(e) = client.confirm_send_set(false).await {
-        error!("Error confirm set");
+    client.set_urc_source(urc_subscription);
+    let config = LoraConfig {
+        region: Some(LoraRegion::Eu868),
+        class: Some(LoraClass::ClassC),
+        adr: Some(false),
+        data_rate: Some(5),
+        confirm: Some(false),
+        auto_join: Some(false),
+        ..Default::default()
+    };
+    let report = client.apply_config(&config).await;
+    if report.is_ok() {
+        info!("Lora config applied, {:?} settings written", report.written());
     } else {
-        info!("Lora send ACK set to false");
+        error!("Error applying lora config");
     }
 
-    if let Err(e) = client.auto_join_set(false).await {
-        error!("Error setting auto join");
-    } else {
-        info!("Auto join disabled");
-    }
-
-    if let Err(e) = client.lora_join_otaa().await {
-        error!("Error joining");
-    } else {
-        info!("Started joining OTAA");
-    }
-
-    let mut joined = false;
-    for _i in 0..100 {
-        let status = client.lora_join_status().await;
-        match status {
-            Ok(status) => match status {
-                LoraJoiningStatus::Joined => {
-                    info!("Joined");
-                    joined = true;
-                    break;
-                }
-                LoraJoiningStatus::Joining => {
-                    info!("Joining");
-                }
-                LoraJoiningStatus::JoinFailed => {
-                    info!("Join failed");
-                }
-                LoraJoiningStatus::InAbpModeError => {
-                    error!("In ABP mode");
-                }
-                LoraJoiningStatus::BusyError => {
-                    error!("Busy");
-                }
-                LoraJoiningStatus::Unknown => {
-                    error!("Unknown error");
-                }
-            },
-
-            Err(e) => {
-                error!("Error getting join status");
-            }
+    let credentials = OtaaCredentials {
+        dev_eui: DEV_EUI,
+        app_eui: 0x0,
+        app_key: APP_KEY,
+    };
+    match client.join_otaa(&credentials, &JoinPolicy::default()).await {
+        Ok(JoinOutcome::Joined { attempts }) => info!("Joined after {:?} attempts", attempts),
+        Ok(JoinOutcome::JoinFailed { attempts }) => {
+            error!("Join failed after {:?} attempts", attempts);
+            return;
+        }
+        Ok(JoinOutcome::TimedOut { attempts }) => {
+            error!("Join timed out after {:?} attempts", attempts);
+            return;
+        }
+        Ok(JoinOutcome::Busy) => {
+            error!("Busy");
+            return;
+        }
+        Ok(JoinOutcome::InAbpMode) => {
+            error!("In ABP mode");
+            return;
+        }
+        Err(e) => {
+            error!("Error joining");
+            return;
         }
-        Timer::after(Duration::from_secs(1)).await;
     }
 
-    if !joined {
-        error!("Failed to join");
-        return;
-    }
     let mut uplink_frame_count = 0;
-    let mut downlink_frame_count = 0;
     loop {
         let uplink_frame_count_get = client.uplink_frame_count().await;
         if let Ok(uplink_frame_count_get) = uplink_frame_count_get {
@@ -235,35 +185,26 @@ async fn client_task(client: AtMokoClient<'static>) {
             }
             Err(e) => error!("Error sending"),
         }
-        for _i in 0..4 {
-            let downlink_frame_count_get = client.downlink_frame_count().await;
-            if let Ok(downlink_frame_count_get) = downlink_frame_count_get {
-                if downlink_frame_count_get != downlink_frame_count {
-                    info!("Downlink frame count changed: {:?}", downlink_frame_count_get);
-                    downlink_frame_count = downlink_frame_count_get;
-                    let recv = client.receive().await;
-                    match recv {
-                        Ok(recv) => {
-                            match recv {
-                                LoraReceivedBytes::None => {
-                                    info!("No bytes received");
-                                },
-                                LoraReceivedBytes::Data(data) => {
-                                    info!("Received {:?} bytes, {:?} RSSI, {:?} SNR, {:?} PORT", data.length, data.rssi, data.snr, data.port);
-                                    let bytes = *data.data;
-                                    let l = core::str::from_utf8(&bytes[0..(data.length as usize)]).unwrap();
-                                    info!("Bytes as string: {:?}", l);
-                                }
-                                LoraReceivedBytes::Ack(ack) => {
-                                    info!("Received ACK, {:?} RSSI, {:?} SNR", ack.rssi, ack.snr);
-                                }
-                            }
-                        }
-                        Err(e) => error!("Error receiving"),
+        // Class C, so downlinks can arrive at any time until the next uplink
+        while let Ok(recv) = with_timeout(Duration::from_secs(20), client.next_downlink()).await {
+            match recv {
+                Ok(LoraReceivedBytes::None) => {
+                    info!("No bytes received");
+                }
+                Ok(LoraReceivedBytes::Data(data)) => {
+                    info!("Received {:?} bytes, {:?} RSSI, {:?} SNR, {:?} PORT", data.length, data.rssi, data.snr, data.port);
+                    if let Ok(l) = core::str::from_utf8(&data.data) {
+                        info!("Bytes as string: {:?}", l);
                     }
                 }
+                Ok(LoraReceivedBytes::Ack(ack)) => {
+                    info!("Received ACK, {:?} RSSI, {:?} SNR", ack.rssi, ack.snr);
+                }
+                Err(e) => {
+                    error!("Error receiving");
+                    break;
+                }
             }
-            Timer::after(Duration::from_secs(5)).await;
         }
     }
 }
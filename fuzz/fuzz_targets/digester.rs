@@ -0,0 +1,26 @@
+#![no_main]
+
+use atat::Digester;
+use libfuzzer_sys::fuzz_target;
+use moko_mkl62ba_at_commands::digester::{DigesterState, MokoDigester};
+
+/// Bytes a module frame can start with. Lines starting with anything else are garbage.
+const FRAME_START: &[u8] = b"+=AENO\r\n";
+
+fuzz_target!(|data: &[u8]| {
+    let state = DigesterState::new();
+    let mut digester = MokoDigester::new(&state);
+    let mut buf = data;
+    while !buf.is_empty() {
+        let (_, len) = digester.digest(buf);
+        assert!(len <= buf.len(), "consumed {} of {} bytes", len, buf.len());
+        if len == 0 {
+            // Waiting for more input is only allowed when the data could still become a frame
+            let garbage = !FRAME_START.contains(&buf[0]);
+            let complete_line = buf.windows(2).any(|w| w == b"\r\n");
+            assert!(!(garbage && complete_line), "stuck on garbage {:?}", buf);
+            break;
+        }
+        buf = &buf[len..];
+    }
+});
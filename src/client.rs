@@ -1,26 +1,742 @@
 #[cfg(feature = "async")]
 pub mod asynch {
+    use crate::digester::DigesterState;
+    use crate::error::Error;
+    use crate::general::session::Session;
+    use crate::general::types::{BootBanner, ModuleInfo, ResetReason};
+    use crate::lora::config::LoraConfig;
+    use crate::lora::responses::{
+        LoraReceivedBytes, LoraReceivedBytesAckResponse, LoraReceivedBytesDataResponse,
+    };
+    use crate::lora::tx_limits::TxLimits;
+    use crate::lora::types::DutyCyclePolicy;
+    use crate::urc::{URCMessages, UrcSource};
+    use atat::asynch::AtatClient;
     pub use atat::asynch::Client;
-    use atat::Error;
+    use atat::AtatCmd;
+    #[cfg(feature = "debug")]
     use defmt::error;
+    use embassy_time::{Duration, Instant, Timer};
     pub use embedded_io::asynch::Write;
+    use heapless::Deque;
+
+    /// Downlinks kept until the application picks them up
+    pub const DOWNLINK_QUEUE_LEN: usize = 4;
+
+    /// How often URCs are checked while waiting on one
+    pub(crate) const URC_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Wait between `AT` attempts while verifying the module answers
+    const VERIFY_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+    /// How long recovery waits for the rest of the boot banner after an unexpected reset
+    const RECOVERY_BOOT_TIMEOUT: Duration = Duration::from_secs(5);
 
     pub struct MokoMkl62BaClient<'a, W: Write, const INGRESS_BUF_SIZE: usize> {
         pub(crate) client: Client<'a, W, INGRESS_BUF_SIZE>,
+        pub(crate) digester: &'a DigesterState,
+        pub(crate) urc_source: Option<&'a mut (dyn UrcSource + 'a)>,
+        pub(crate) next_tx_at: Option<Instant>,
+        pub(crate) duty_cycle_policy: DutyCyclePolicy,
+        pub(crate) tx_limits: TxLimits,
+        pub(crate) downlinks: Deque<LoraReceivedBytes, DOWNLINK_QUEUE_LEN>,
+        pub(crate) boot: BootBanner,
+        pub(crate) session: Session,
     }
 
     impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> MokoMkl62BaClient<'a, W, INGRESS_BUF_SIZE> {
+        /// Reset the module and turn echo off. Failures are only logged, see
+        /// [builder](Self::builder) for more control.
+        ///
+        /// `digester` is the state of the [MokoDigester](crate::digester::MokoDigester) the
+        /// ingress of `client` was split with.
         pub async fn new(
             client: Client<'a, W, INGRESS_BUF_SIZE>,
+            digester: &'a DigesterState,
         ) -> Result<MokoMkl62BaClient<'a, W, INGRESS_BUF_SIZE>, Error> {
-            let mut s = Self { client };
-            if s.reset().await.is_err() {
+            Self::builder(client, digester).build().await
+        }
+
+        /// Like [new](Self::new), but waits for the boot banner after the reset before sending
+        /// anything else to the module.
+        pub async fn new_with_urc_source(
+            client: Client<'a, W, INGRESS_BUF_SIZE>,
+            digester: &'a DigesterState,
+            urc_source: &'a mut (dyn UrcSource + 'a),
+            boot_timeout: Duration,
+        ) -> Result<MokoMkl62BaClient<'a, W, INGRESS_BUF_SIZE>, Error> {
+            Self::builder(client, digester)
+                .urc_source(urc_source)
+                .boot_timeout(boot_timeout)
+                .build()
+                .await
+        }
+
+        pub fn builder(
+            client: Client<'a, W, INGRESS_BUF_SIZE>,
+            digester: &'a DigesterState,
+        ) -> MokoMkl62BaClientBuilder<'a, W, INGRESS_BUF_SIZE> {
+            MokoMkl62BaClientBuilder::new(client, digester)
+        }
+
+        fn from_client(
+            client: Client<'a, W, INGRESS_BUF_SIZE>,
+            digester: &'a DigesterState,
+            urc_source: Option<&'a mut (dyn UrcSource + 'a)>,
+        ) -> Self {
+            Self {
+                client,
+                digester,
+                urc_source,
+                next_tx_at: None,
+                duty_cycle_policy: DutyCyclePolicy::default(),
+                tx_limits: TxLimits::default(),
+                downlinks: Deque::new(),
+                boot: BootBanner::default(),
+                session: Session::default(),
+            }
+        }
+
+        /// Let the client follow URCs emitted by the module, e.g. `NEXT TX after(s)`.
+        pub fn set_urc_source(&mut self, urc_source: &'a mut (dyn UrcSource + 'a)) {
+            self.urc_source = Some(urc_source);
+        }
+
+        /// Drain all pending URCs from the URC source, if one is set.
+        pub fn poll_urcs(&mut self) {
+            loop {
+                let urc = match self.urc_source.as_mut() {
+                    Some(source) => source.try_next_urc(),
+                    None => None,
+                };
+                match urc {
+                    Some(urc) => self.handle_urc(urc),
+                    None => break,
+                }
+            }
+        }
+
+        pub(crate) fn handle_urc(&mut self, urc: URCMessages) {
+            if urc == URCMessages::SystemStart {
+                self.session.system_start();
+                if self.session.last_reset == Some(ResetReason::Unexpected) {
+                    #[cfg(feature = "debug")]
+                    error!("Moko reset unexpectedly");
+                }
+            }
+            if self.boot.handle(&urc) {
+                return;
+            }
+            match urc {
+                URCMessages::NextTxInSeconds(seconds) => {
+                    self.next_tx_at = Some(Instant::now() + Duration::from_secs(seconds as u64));
+                }
+                URCMessages::Downlink {
+                    port,
+                    rssi,
+                    snr,
+                    data,
+                } => {
+                    self.session.downlink_received = true;
+                    self.queue_downlink(LoraReceivedBytes::Data(LoraReceivedBytesDataResponse {
+                        rssi,
+                        snr,
+                        port,
+                        length: data.len() as u16,
+                        data,
+                    }))
+                }
+                URCMessages::Ack { rssi, snr } => {
+                    self.session.downlink_received = true;
+                    self.queue_downlink(LoraReceivedBytes::Ack(LoraReceivedBytesAckResponse {
+                        rssi,
+                        snr,
+                        ack: "ACK".into(),
+                    }))
+                }
+                _ => {}
+            }
+        }
+
+        /// Oldest downlinks are dropped when the application does not keep up
+        fn queue_downlink(&mut self, downlink: LoraReceivedBytes) {
+            if self.downlinks.is_full() {
+                self.downlinks.pop_front();
+            }
+            self.downlinks.push_back(downlink).ok();
+        }
+
+        /// Next downlink or ACK the module reported on its own, if any
+        pub fn try_next_downlink(&mut self) -> Option<LoraReceivedBytes> {
+            self.poll_urcs();
+            self.downlinks.pop_front()
+        }
+
+        /// Wait for the next downlink or ACK the module reports on its own. Needs a URC source,
+        /// see [set_urc_source](Self::set_urc_source).
+        ///
+        /// The URC source is polled every 50 ms and this only returns once a downlink arrives,
+        /// use [next_downlink_timeout](Self::next_downlink_timeout) to bound the wait.
+        pub async fn next_downlink(&mut self) -> Result<LoraReceivedBytes, Error> {
+            if self.urc_source.is_none() && self.downlinks.is_empty() {
+                return Err(Error::NoUrcSource);
+            }
+            loop {
+                if let Some(downlink) = self.try_next_downlink() {
+                    return Ok(downlink);
+                }
+                Timer::after(URC_POLL_INTERVAL).await;
+            }
+        }
+
+        /// Like [next_downlink](Self::next_downlink), failing with [Error::Timeout] if nothing
+        /// arrives within `timeout`
+        pub async fn next_downlink_timeout(
+            &mut self,
+            timeout: Duration,
+        ) -> Result<LoraReceivedBytes, Error> {
+            if self.urc_source.is_none() && self.downlinks.is_empty() {
+                return Err(Error::NoUrcSource);
+            }
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(downlink) = self.try_next_downlink() {
+                    return Ok(downlink);
+                }
+                if Instant::now() >= deadline {
+                    return Err(Error::Timeout);
+                }
+                Timer::after(URC_POLL_INTERVAL).await;
+            }
+        }
+
+        /// Module details from the last boot banner seen, once it is complete
+        pub fn module_info(&mut self) -> Option<ModuleInfo> {
+            self.poll_urcs();
+            self.boot.module_info()
+        }
+
+        /// Wait until the module has printed its whole boot banner, e.g. after
+        /// [reset](Self::reset). Needs a URC source, see [set_urc_source](Self::set_urc_source).
+        pub async fn wait_for_boot(&mut self, timeout: Duration) -> Result<ModuleInfo, Error> {
+            if self.urc_source.is_none() {
+                return Err(Error::NoUrcSource);
+            }
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(info) = self.module_info() {
+                    return Ok(info);
+                }
+                if Instant::now() >= deadline {
+                    return Err(Error::Timeout);
+                }
+                Timer::after(URC_POLL_INTERVAL).await;
+            }
+        }
+
+        /// Send a command, waking the module first if it is asleep
+        pub(crate) async fn command<Cmd: AtatCmd<LEN>, const LEN: usize>(
+            &mut self,
+            command: &Cmd,
+        ) -> Result<Cmd::Response, Error> {
+            if self.session.sleep == Some(true) {
+                self.wake().await?;
+            }
+            // A code left by an unanswered ERROR line is not this command's
+            self.digester.take_error_code();
+            self.client
+                .send(command)
+                .await
+                .map_err(|e| Error::from_atat(e, self.digester.take_error_code()))
+        }
+
+        /// Number of times the module booted since the client was created, as seen on the URCs
+        pub fn reset_count(&mut self) -> u32 {
+            self.poll_urcs();
+            self.session.resets
+        }
+
+        pub fn last_reset_reason(&mut self) -> Option<ResetReason> {
+            self.poll_urcs();
+            self.session.last_reset
+        }
+
+        /// Joined with [join_otaa](Self::join_otaa) and not reset since
+        pub fn is_joined(&mut self) -> bool {
+            self.poll_urcs();
+            self.session.joined
+        }
+
+        /// Join the network again when recovering from an unexpected reset, if it was joined
+        pub fn set_rejoin_after_reset(&mut self, rejoin: bool) {
+            self.session.rejoin = rejoin;
+        }
+
+        /// Put the module back to sleep after every uplink, once the RX windows have closed. It
+        /// is woken up again before the next command. Not for class C, which listens in between
+        /// uplinks.
+        ///
+        /// [send](Self::send) waits for the RX windows: RX1 delay, a second until RX2 and the
+        /// time a downlink in RX2 can take. A downlink or ACK reported on the URC source ends
+        /// the wait early. With `unverified-commands`, the RX1 delay is read from the module
+        /// before the uplink if the client doesn't know it yet. Otherwise, or if the module
+        /// refuses +RX1_DELAY, the LoRaWAN default of 1 s is assumed.
+        pub fn set_auto_sleep(&mut self, auto_sleep: bool) {
+            self.session.auto_sleep = auto_sleep;
+        }
+
+        /// Put the module back in the state the client left it in if it rebooted on its own:
+        /// echo, every setting applied with [apply_config](Self::apply_config), the network
+        /// session if [set_rejoin_after_reset](Self::set_rejoin_after_reset) is on, and sleep.
+        ///
+        /// Called by [send](Self::send), [receive](Self::receive),
+        /// [join_otaa](Self::join_otaa), [read_config](Self::read_config),
+        /// [apply_config](Self::apply_config) and the
+        /// [UplinkQueue](crate::lora::queue::UplinkQueue). Single getters and setters, the
+        /// channel commands and [sleep](Self::sleep) don't recover: they would read or change
+        /// the module defaults, call this first where that matters. Recovery is not part of
+        /// every command as it sends commands itself.
+        ///
+        /// Needs a URC source to notice the reset. Returns whether there was anything to
+        /// recover.
+        pub async fn recover_if_reset(&mut self) -> Result<bool, Error> {
+            self.poll_urcs();
+            if !self.session.take_recovery() {
+                return Ok(false);
+            }
+            if let Err(e) = self.recover().await {
+                #[cfg(feature = "debug")]
+                error!("Error recovering Moko from a reset");
+                self.session.recovery_failed();
+                return Err(e);
+            }
+            Ok(true)
+        }
+
+        async fn recover(&mut self) -> Result<(), Error> {
+            // Not fatal, the banner may have been cut short by the brown out
+            self.wait_for_boot(RECOVERY_BOOT_TIMEOUT).await.ok();
+            self.tx_limits = TxLimits::default();
+            if let Some(echo) = self.session.echo {
+                self.at_echo_set(echo).await?;
+            }
+            if let Some(config) = self.session.config.clone() {
+                if let Some(e) = self.apply_config_inner(&config).await.first_error() {
+                    return Err(e.clone());
+                }
+            }
+            if let Some((credentials, policy)) = self.session.take_rejoin() {
+                if !self
+                    .join_otaa_inner(&credentials, &policy)
+                    .await?
+                    .is_joined()
+                {
+                    #[cfg(feature = "debug")]
+                    error!("Could not join again after the reset");
+                }
+            }
+            if self.session.take_restore_sleep() {
+                self.sleep().await?;
+            }
+            Ok(())
+        }
+
+        /// What [send](Self::send) does while the module does not allow the next uplink yet
+        pub fn set_duty_cycle_policy(&mut self, policy: DutyCyclePolicy) {
+            self.duty_cycle_policy = policy;
+        }
+
+        /// Time left until the module allows the next uplink, `None` if it can go now. Needs a
+        /// URC source, the module announces the wait with `NEXT TX after(s)` after an uplink.
+        pub fn duty_cycle_remaining(&mut self) -> Option<Duration> {
+            self.poll_urcs();
+            let remaining = self.next_tx_delay();
+            (remaining > Duration::from_ticks(0)).then_some(remaining)
+        }
+
+        /// Time left until the module allows the next transmission, as reported by the last
+        /// `NEXT TX after(s)` URC.
+        pub fn next_tx_delay(&self) -> Duration {
+            match self.next_tx_at {
+                Some(at) => at
+                    .checked_duration_since(Instant::now())
+                    .unwrap_or_default(),
+                None => Duration::from_ticks(0),
+            }
+        }
+    }
+
+    /// Step by step setup of a [MokoMkl62BaClient]
+    ///
+    /// By default the module is reset and echo is turned off, with failures only logged.
+    pub struct MokoMkl62BaClientBuilder<'a, W: Write, const INGRESS_BUF_SIZE: usize> {
+        client: Client<'a, W, INGRESS_BUF_SIZE>,
+        digester: &'a DigesterState,
+        urc_source: Option<&'a mut (dyn UrcSource + 'a)>,
+        reset: bool,
+        boot_timeout: Duration,
+        verify_attempts: u8,
+        echo: Option<bool>,
+        config: Option<LoraConfig>,
+        strict: bool,
+        rejoin: bool,
+        auto_sleep: bool,
+        duty_cycle_policy: DutyCyclePolicy,
+    }
+
+    impl<'a, W: Write, const INGRESS_BUF_SIZE: usize>
+        MokoMkl62BaClientBuilder<'a, W, INGRESS_BUF_SIZE>
+    {
+        /// See [MokoMkl62BaClient::new] for `digester`
+        pub fn new(client: Client<'a, W, INGRESS_BUF_SIZE>, digester: &'a DigesterState) -> Self {
+            Self {
+                client,
+                digester,
+                urc_source: None,
+                reset: true,
+                boot_timeout: Duration::from_secs(5),
+                verify_attempts: 0,
+                echo: Some(false),
+                config: None,
+                strict: false,
+                rejoin: false,
+                auto_sleep: false,
+                duty_cycle_policy: DutyCyclePolicy::default(),
+            }
+        }
+
+        /// Follow URCs from the start. Also makes the client wait for the boot banner after
+        /// the reset.
+        pub fn urc_source(mut self, urc_source: &'a mut (dyn UrcSource + 'a)) -> Self {
+            self.urc_source = Some(urc_source);
+            self
+        }
+
+        /// Don't reset the module
+        pub fn skip_reset(mut self) -> Self {
+            self.reset = false;
+            self
+        }
+
+        /// How long to wait for the boot banner after the reset, if a URC source is set
+        pub fn boot_timeout(mut self, boot_timeout: Duration) -> Self {
+            self.boot_timeout = boot_timeout;
+            self
+        }
+
+        /// Check the module answers `AT`, trying up to `retries` more times
+        pub fn verify_comms(mut self, retries: u8) -> Self {
+            self.verify_attempts = retries.saturating_add(1);
+            self
+        }
+
+        /// Echo state to set, `None` leaves it as is
+        pub fn echo(mut self, echo: Option<bool>) -> Self {
+            self.echo = echo;
+            self
+        }
+
+        /// Write this profile once the module is up
+        pub fn config(mut self, config: LoraConfig) -> Self {
+            self.config = Some(config);
+            self
+        }
+
+        /// See [set_rejoin_after_reset](MokoMkl62BaClient::set_rejoin_after_reset)
+        pub fn rejoin_after_reset(mut self, rejoin: bool) -> Self {
+            self.rejoin = rejoin;
+            self
+        }
+
+        /// See [set_auto_sleep](MokoMkl62BaClient::set_auto_sleep)
+        pub fn auto_sleep(mut self, auto_sleep: bool) -> Self {
+            self.auto_sleep = auto_sleep;
+            self
+        }
+
+        /// See [set_duty_cycle_policy](MokoMkl62BaClient::set_duty_cycle_policy)
+        pub fn duty_cycle_policy(mut self, policy: DutyCyclePolicy) -> Self {
+            self.duty_cycle_policy = policy;
+            self
+        }
+
+        /// Fail [build](Self::build) on the first step that fails, instead of logging it
+        pub fn strict(mut self, strict: bool) -> Self {
+            self.strict = strict;
+            self
+        }
+
+        pub async fn build(self) -> Result<MokoMkl62BaClient<'a, W, INGRESS_BUF_SIZE>, Error> {
+            let strict = self.strict;
+            let wait_for_boot = self.urc_source.is_some();
+            let mut client =
+                MokoMkl62BaClient::from_client(self.client, self.digester, self.urc_source);
+            client.set_rejoin_after_reset(self.rejoin);
+            client.set_auto_sleep(self.auto_sleep);
+            client.set_duty_cycle_policy(self.duty_cycle_policy);
+
+            if self.reset {
+                let reset = match client.reset().await {
+                    Ok(()) if wait_for_boot => client.wait_for_boot(self.boot_timeout).await,
+                    Ok(()) => Ok(()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = reset {
+                    #[cfg(feature = "debug")]
+                    error!("Error resetting Moko");
+                    if strict {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if self.verify_attempts > 0 {
+                let mut verified = Err(Error::Timeout);
+                for _ in 0..self.verify_attempts {
+                    verified = match client.verify_com_is_working().await {
+                        Ok(true) => Ok(()),
+                        Ok(false) => Err(Error::Timeout),
+                        Err(e) => Err(e),
+                    };
+                    if verified.is_ok() {
+                        break;
+                    }
+                    Timer::after(VERIFY_RETRY_DELAY).await;
+                }
+                if let Err(e) = verified {
+                    #[cfg(feature = "debug")]
+                    error!("Moko does not answer AT");
+                    if strict {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if let Some(echo) = self.echo {
+                let set = match client.at_echo_set(echo).await {
+                    Ok(on) if on == echo => Ok(()),
+                    Ok(_) => Err(Error::InvalidArgument),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = set {
+                    #[cfg(feature = "debug")]
+                    error!("Error setting echo");
+                    if strict {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if let Some(config) = &self.config {
+                let report = client.apply_config(config).await;
+                if let Some(e) = report.first_error() {
+                    #[cfg(feature = "debug")]
+                    error!("Error applying LoRa config");
+                    if strict {
+                        return Err(e.clone());
+                    }
+                }
+            }
+
+            Ok(client)
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use crate::digester::DigesterState;
+    use crate::error::Error;
+    use crate::general::session::Session;
+    use crate::lora::tx_limits::TxLimits;
+    use atat::blocking::AtatClient;
+    pub use atat::blocking::Client;
+    use atat::AtatCmd;
+    #[cfg(feature = "debug")]
+    use defmt::error;
+    pub use embedded_io::blocking::Write;
+
+    /// Blocking counterpart of [MokoMkl62BaClient](crate::client::asynch::MokoMkl62BaClient),
+    /// for targets without an async executor.
+    ///
+    /// It has the same commands, [read_config](Self::read_config),
+    /// [apply_config](Self::apply_config) and, with `unverified-commands`, the channel plan, but
+    /// none of the parts that need a timer or the URC channel: no join driver, duty cycle
+    /// tracking, recovery from an unexpected reset, downlink URCs, auto sleep or uplink queue.
+    /// With `unverified-commands`, the wait for the module to switch baud rates is left to the
+    /// `reconfigure` closure of `baud_rate_set`.
+    pub struct MokoMkl62BaBlockingClient<'a, W: Write, const INGRESS_BUF_SIZE: usize> {
+        pub(crate) client: Client<'a, W, INGRESS_BUF_SIZE>,
+        pub(crate) digester: &'a DigesterState,
+        pub(crate) tx_limits: TxLimits,
+        pub(crate) session: Session,
+    }
+
+    impl<'a, W: Write, const INGRESS_BUF_SIZE: usize>
+        MokoMkl62BaBlockingClient<'a, W, INGRESS_BUF_SIZE>
+    {
+        /// Reset the module and turn echo off. `digester` is the state of the
+        /// [MokoDigester](crate::digester::MokoDigester) the ingress of `client` was split with.
+        pub fn new(
+            client: Client<'a, W, INGRESS_BUF_SIZE>,
+            digester: &'a DigesterState,
+        ) -> Result<MokoMkl62BaBlockingClient<'a, W, INGRESS_BUF_SIZE>, Error> {
+            let mut s = Self {
+                client,
+                digester,
+                tx_limits: TxLimits::default(),
+                session: Session::default(),
+            };
+            if s.reset().is_err() {
+                #[cfg(feature = "debug")]
                 error!("Error resetting Moko");
             }
-            if s.at_echo_set(false).await.is_err() {
-                error!("Error settign echo to false");
+            if s.at_echo_set(false).is_err() {
+                #[cfg(feature = "debug")]
+                error!("Error setting echo to false");
             }
             Ok(s)
         }
+
+        /// Send a command, waking the module first if it is asleep
+        pub(crate) fn command<Cmd: AtatCmd<LEN>, const LEN: usize>(
+            &mut self,
+            command: &Cmd,
+        ) -> Result<Cmd::Response, Error> {
+            if self.session.sleep == Some(true) {
+                self.wake()?;
+            }
+            // A code left by an unanswered ERROR line is not this command's
+            self.digester.take_error_code();
+            self.client
+                .send(command)
+                .map_err(|e| Error::from_atat(e, self.digester.take_error_code()))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "async"))]
+mod tests {
+    use crate::error::Error;
+    use crate::general::types::ResetReason;
+    use crate::lora::responses::LoraReceivedBytes;
+    use crate::lora::types::{DutyCyclePolicy, JoinPolicy, LoraRegion, OtaaCredentials};
+    use crate::sim::{run_client, Mkl62baSim};
+    use embassy_time::{Duration, Timer};
+    use std::vec::Vec;
+
+    fn credentials() -> OtaaCredentials {
+        OtaaCredentials {
+            dev_eui: 0x68419fa0f7e74b0d,
+            app_eui: 0,
+            app_key: 0xd65b042878144e038a744359c7cd1f9d,
+        }
+    }
+
+    fn policy() -> JoinPolicy {
+        JoinPolicy {
+            poll_interval_ms: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn builder() {
+        let sim = Mkl62baSim::new();
+        run_client(
+            &sim,
+            |builder| builder.verify_comms(1).strict(true),
+            |mut client, _| async move {
+                assert_eq!(client.module_info().unwrap().region, LoraRegion::Eu868);
+                assert_eq!(client.reset_count(), 1);
+                assert_eq!(client.last_reset_reason(), Some(ResetReason::Requested));
+            },
+        );
+        sim.with_state(|s| {
+            assert_eq!(s.resets, 1);
+            assert!(!s.echo);
+        });
+    }
+
+    #[test]
+    fn downlinks() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.joined = true);
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, sim| async move {
+                assert_eq!(client.try_next_downlink(), None);
+                client.send(0, 8, b"Hello").await.unwrap();
+                sim.inject_downlink(8, -102, -4.0, b"12");
+                match client.next_downlink().await.unwrap() {
+                    LoraReceivedBytes::Data(data) => assert_eq!(&data.data[..], b"12"),
+                    other => panic!("unexpected downlink {:?}", other),
+                }
+                assert_eq!(
+                    client
+                        .next_downlink_timeout(Duration::from_millis(100))
+                        .await,
+                    Err(Error::Timeout)
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn recovery() {
+        let sim = Mkl62baSim::new();
+        run_client(
+            &sim,
+            |builder| builder.rejoin_after_reset(true).strict(true),
+            |mut client, sim| async move {
+                let outcome = client.join_otaa(&credentials(), &policy()).await.unwrap();
+                assert!(outcome.is_joined());
+
+                // Brown out, the next uplink recovers the module first
+                sim.boot();
+                client.send(0, 8, b"Again").await.unwrap();
+                assert_eq!(client.reset_count(), 2);
+                assert_eq!(client.last_reset_reason(), Some(ResetReason::Unexpected));
+                assert!(client.is_joined());
+            },
+        );
+        sim.with_state(|s| {
+            assert_eq!(s.resets, 2);
+            assert!(!s.echo);
+            assert!(s.joined);
+            assert_eq!(s.uplinks, [(8, Vec::from(&b"Again"[..]))]);
+        });
+    }
+
+    #[test]
+    fn duty_cycle() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| {
+            s.joined = true;
+            s.next_tx_after = Some(60);
+        });
+        run_client(
+            &sim,
+            |builder| {
+                builder
+                    .skip_reset()
+                    .duty_cycle_policy(DutyCyclePolicy::Reject)
+            },
+            |mut client, _| async move {
+                client.send(0, 8, b"Hello").await.unwrap();
+                // The module asked to hold off for a minute
+                while client.duty_cycle_remaining().is_none() {
+                    Timer::after(Duration::from_millis(10)).await;
+                }
+                match client.send(0, 8, b"Early").await {
+                    Err(Error::DutyCycleRestricted { retry_after }) => {
+                        assert!(retry_after > 50_000 && retry_after <= 60_000)
+                    }
+                    other => panic!("unexpected send result {:?}", other),
+                }
+            },
+        );
+        sim.with_state(|s| assert_eq!(s.uplinks.len(), 1));
     }
 }
@@ -11,46 +11,84 @@ use atat::{
 };
 
 use crate::urc::URCMessages;
+use core::sync::atomic::{AtomicBool, AtomicI16, Ordering};
 #[cfg(feature = "debug")]
 use defmt::{debug, info};
 #[cfg(feature = "debug")]
 use heapless::String;
 
-#[derive(Default)]
-pub struct MokoDigester {}
+/// State a [MokoDigester] shares with the client it feeds, one per module.
+///
+/// The digester is moved into the atat ingress, so anything the client needs from it goes
+/// through here. Keep it in a `static` or another place that outlives both.
+#[derive(Debug, Default)]
+pub struct DigesterState {
+    /// Last error code matched by [MokoDigester::custom_error], 0 if none is pending
+    error_code: AtomicI16,
+    /// Set while the client waits for the answer to +RECVB=?
+    receive_pending: AtomicBool,
+}
+
+impl DigesterState {
+    pub const fn new() -> Self {
+        Self {
+            error_code: AtomicI16::new(0),
+            receive_pending: AtomicBool::new(false),
+        }
+    }
+
+    /// Code of the last `ERROR(-n)` seen by the digester, cleared on read.
+    ///
+    /// atat hands custom errors to the client without their payload, this is how the code makes
+    /// it to [Error](crate::error::Error).
+    pub fn take_error_code(&self) -> Option<i16> {
+        match self.error_code.swap(0, Ordering::Relaxed) {
+            0 => None,
+            code => Some(code),
+        }
+    }
+
+    /// Mark that a +RECVB=? command is in flight, so the +RECVB: line that follows is its response
+    /// and not an unsolicited downlink.
+    pub fn set_receive_pending(&self, pending: bool) {
+        self.receive_pending.store(pending, Ordering::Relaxed);
+    }
+}
+
+pub struct MokoDigester<'a> {
+    state: &'a DigesterState,
+    /// An echoed command was seen since the last `+ATE: OFF`
+    echo: bool,
+}
+
+impl<'a> MokoDigester<'a> {
+    /// Digester for one module, `state` is handed to the client of the same module
+    pub fn new(state: &'a DigesterState) -> Self {
+        Self { state, echo: false }
+    }
 
-impl MokoDigester {
+    /// Matches `ERROR(-n)` as well as `ERROR (-n)`
     pub fn custom_error(buf: &[u8]) -> Result<(&[u8], usize), ParseError> {
-        let (_reminder, (head, data, tail)) = branch::alt((
-            sequence::tuple((
-                combinator::success(&b""[..]),
-                bytes::streaming::tag(b"ERROR(-1)"),
-                bytes::streaming::tag(b"\r\n"),
-            )),
-            sequence::tuple((
-                combinator::success(&b""[..]),
-                bytes::streaming::tag(b"ERROR(-2)"),
-                bytes::streaming::tag(b"\r\n"),
-            )),
-            sequence::tuple((
-                combinator::success(&b""[..]),
-                bytes::streaming::tag(b"ERROR(-3)"),
-                bytes::streaming::tag(b"\r\n"),
-            )),
-            sequence::tuple((
-                combinator::success(&b""[..]),
-                bytes::streaming::tag(b"ERROR(-5)"),
-                bytes::streaming::tag(b"\r\n"),
-            )),
-            sequence::tuple((
-                combinator::success(&b""[..]),
-                bytes::streaming::tag(b"ERROR(-7)"),
-                bytes::streaming::tag(b"\r\n"),
-            )),
+        let (_reminder, (data, tail)) = sequence::tuple((
+            combinator::recognize(sequence::tuple((
+                bytes::streaming::tag(b"ERROR"),
+                combinator::opt(bytes::streaming::tag(b" ")),
+                bytes::streaming::tag(b"(-"),
+                character::streaming::digit1,
+                bytes::streaming::tag(b")"),
+            ))),
+            bytes::streaming::tag(b"\r\n"),
         ))(buf)?;
         #[cfg(feature = "debug")]
         debug!("Custom error {:?}", LossyStr(data));
-        Ok((data, head.len() + data.len() + tail.len()))
+        Ok((data, data.len() + tail.len()))
+    }
+
+    /// Numeric code of an `ERROR(-n)` line, e.g. -3 for `ERROR(-3)`
+    pub fn parse_error_code(buf: &[u8]) -> Option<i16> {
+        let start = buf.iter().position(|b| *b == b'(')? + 1;
+        let end = start + buf[start..].iter().position(|b| *b == b')')?;
+        core::str::from_utf8(&buf[start..end]).ok()?.parse().ok()
     }
 
     pub fn custom_success(buf: &[u8]) -> Result<(&[u8], usize), ParseError> {
@@ -133,16 +171,32 @@ impl MokoDigester {
     }
 }
 
-impl Digester for MokoDigester {
+impl Digester for MokoDigester<'_> {
     fn digest<'a>(&mut self, input: &'a [u8]) -> (DigestResult<'a>, usize) {
         #[cfg(feature = "debug")]
         let s = LossyStr(input);
         #[cfg(feature = "debug")]
         info!("Digesting: {:?}", s);
 
-        // Incomplete. Eat the echo and do nothing else.
+        // Incomplete, wait for more input
         let incomplete = (DigestResult::None, 0);
 
+        // 1. Echoed command. Dropped whatever +ATE says, the module turns echo back on when it
+        // resets. The first echo after `+ATE: OFF` is passed on as a URC, see URCMessages::AteOn.
+        if input.starts_with(b"AT") {
+            return match input.windows(2).position(|w| w == b"\r\n") {
+                Some(pos) if !self.echo => {
+                    self.echo = true;
+                    (DigestResult::Urc(&input[..pos]), pos + 2)
+                }
+                Some(pos) => (DigestResult::None, pos + 2),
+                None => incomplete,
+            };
+        }
+        if input.starts_with(b"+ATE: OFF\r\n") {
+            self.echo = false;
+        }
+
         // Stray OK\r\n
         if input == b"OK\r\n" {
             return (DigestResult::None, 4);
@@ -156,10 +210,15 @@ impl Digester for MokoDigester {
         }
 
         // 2. Match for URC's
-        match <URCMessages as Parser>::parse(input) {
-            Ok((urc, len)) => return (DigestResult::Urc(urc), len),
-            Err(ParseError::Incomplete) => return incomplete,
-            _ => {}
+        // +RECVB: is only a URC when it was not asked for with +RECVB=?
+        let is_receive_response =
+            self.state.receive_pending.load(Ordering::Relaxed) && input.starts_with(b"+RECVB: ");
+        if !is_receive_response {
+            match <URCMessages as Parser>::parse(input) {
+                Ok((urc, len)) => return (DigestResult::Urc(urc), len),
+                Err(ParseError::Incomplete) => return incomplete,
+                _ => {}
+            }
         }
 
         // 3. Parse for success responses
@@ -174,10 +233,13 @@ impl Digester for MokoDigester {
         // Custom error matches first, if any
         match (MokoDigester::custom_error)(input) {
             Ok((response, len)) => {
+                if let Some(code) = MokoDigester::parse_error_code(response) {
+                    self.state.error_code.store(code, Ordering::Relaxed);
+                }
                 return (
                     DigestResult::Response(Err(InternalError::Custom(response))),
                     len,
-                )
+                );
             }
             Err(ParseError::Incomplete) => return incomplete,
             _ => {}
@@ -188,7 +250,210 @@ impl Digester for MokoDigester {
             return (result, len);
         }
 
-        // No matches at all.
-        incomplete
+        // No matches at all. Drop the line, so garbage can't stall the ingress buffer.
+        match input.windows(2).position(|w| w == b"\r\n") {
+            Some(pos) => (DigestResult::None, pos + 2),
+            None => incomplete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::digester::{DigesterState, MokoDigester};
+    use atat::{DigestResult, Digester, InternalError};
+    use heapless::Vec;
+
+    /// Module transcripts, each with what the digester should make of it
+    const CORPUS: &[(&[u8], DigestResult<'static>)] = &[
+        (
+            b"=====================SYSTEM START=====================\r\n\r\n",
+            DigestResult::Urc(b"SYSTEM START"),
+        ),
+        (
+            b"===================SOFT VERSION:1.0.2==============\r\n\r\n",
+            DigestResult::Urc(b"SOFT VERSION:1.0.2"),
+        ),
+        (
+            b"===================LORA VERSION:103===============\r\n\r\n",
+            DigestResult::Urc(b"LORA VERSION:103"),
+        ),
+        (
+            b"===================LORA REGION:EU868==================\r\n",
+            DigestResult::Urc(b"LORA REGION:EU868"),
+        ),
+        (
+            b"NEXT TX after(s):12\r\n",
+            DigestResult::Urc(b"NEXT TX after(s):12"),
+        ),
+        (
+            b"+RECVB: -102:-4.0:8:2:3132\r\n",
+            DigestResult::Urc(b"+RECVB: -102:-4.0:8:2:3132"),
+        ),
+        (b"+AT: OK\r\n", DigestResult::Response(Ok(b"OK"))),
+        (b"+ATE: OFF\r\n", DigestResult::Response(Ok(b"+ATE: OFF"))),
+        (
+            b"+JOIN_STD: JOINED\r\n",
+            DigestResult::Response(Ok(b"+JOIN_STD: JOINED")),
+        ),
+        (b"+UP_CNT: 3\r\n", DigestResult::Response(Ok(b"+UP_CNT: 3"))),
+        (
+            b"+DOWN_CNT: 1\r\n",
+            DigestResult::Response(Ok(b"+DOWN_CNT: 1")),
+        ),
+        (
+            b"ERROR(-3)\r\n",
+            DigestResult::Response(Err(InternalError::Custom(b"ERROR(-3)"))),
+        ),
+        (
+            b"ERROR (-5)\r\n",
+            DigestResult::Response(Err(InternalError::Custom(b"ERROR (-5)"))),
+        ),
+    ];
+
+    /// Digest `input` the way the ingress does, until nothing more is consumed
+    fn digest_all<'a>(state: &DigesterState, input: &'a [u8]) -> (Vec<DigestResult<'a>, 8>, usize) {
+        let mut digester = MokoDigester::new(state);
+        let mut results = Vec::new();
+        let mut consumed = 0;
+        loop {
+            let (result, len) = digester.digest(&input[consumed..]);
+            assert!(len <= input.len() - consumed);
+            if len == 0 {
+                return (results, consumed);
+            }
+            consumed += len;
+            if result != DigestResult::None {
+                results.push(result).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn corpus() {
+        for (input, expected) in CORPUS {
+            let state = DigesterState::new();
+            let (results, consumed) = digest_all(&state, input);
+            assert_eq!(results.as_slice(), core::slice::from_ref(expected));
+            assert_eq!(consumed, input.len());
+            let code = match expected {
+                DigestResult::Response(Err(InternalError::Custom(e))) => {
+                    MokoDigester::parse_error_code(e)
+                }
+                _ => None,
+            };
+            assert_eq!(state.take_error_code(), code);
+            assert_eq!(state.take_error_code(), None);
+        }
+    }
+
+    #[test]
+    fn receive_response() {
+        // Answer to +RECVB=? instead of a downlink URC, only for the module that asked
+        let input = b"+RECVB: -102:-4.0:8:2:3132\r\n";
+        let (state, other) = (DigesterState::new(), DigesterState::new());
+        state.set_receive_pending(true);
+        assert_eq!(
+            MokoDigester::new(&state).digest(input),
+            (
+                DigestResult::Response(Ok(b"-102:-4.0:8:2:3132\r\n")),
+                input.len()
+            )
+        );
+        assert_eq!(
+            MokoDigester::new(&other).digest(input),
+            (
+                DigestResult::Urc(b"+RECVB: -102:-4.0:8:2:3132"),
+                input.len()
+            )
+        );
+        state.set_receive_pending(false);
+        assert_eq!(
+            MokoDigester::new(&state).digest(input),
+            (
+                DigestResult::Urc(b"+RECVB: -102:-4.0:8:2:3132"),
+                input.len()
+            )
+        );
+    }
+
+    #[test]
+    fn partial_input() {
+        let state = DigesterState::new();
+        let mut digester = MokoDigester::new(&state);
+        for (input, _) in CORPUS {
+            for end in 1..input.len() {
+                let (result, len) = digester.digest(&input[..end]);
+                assert_eq!(result, DigestResult::None);
+                assert_eq!(len, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn stray_ok() {
+        let state = DigesterState::new();
+        let mut digester = MokoDigester::new(&state);
+        assert_eq!(digester.digest(b"OK\r\n"), (DigestResult::None, 4));
+    }
+
+    #[test]
+    fn interleaved_urcs() {
+        let input = b"NEXT TX after(s):5\r\n+UP_CNT: 3\r\nNEXT TX after(s):4\r\n";
+        let (results, consumed) = digest_all(&DigesterState::new(), input);
+        assert_eq!(
+            results.as_slice(),
+            &[
+                DigestResult::Urc(b"NEXT TX after(s):5"),
+                DigestResult::Response(Ok(b"+UP_CNT: 3")),
+                DigestResult::Urc(b"NEXT TX after(s):4"),
+            ]
+        );
+        assert_eq!(consumed, input.len());
+    }
+
+    #[test]
+    fn echo() {
+        let input = b"AT+UP_CNT=?\r\n+UP_CNT: 3\r\nAT+DOWN_CNT=?\r\n+DOWN_CNT: 1\r\n\
+            AT+ATE=OFF\r\n+ATE: OFF\r\nAT\r\n+AT: OK\r\n";
+        let (results, consumed) = digest_all(&DigesterState::new(), input);
+        assert_eq!(
+            results.as_slice(),
+            &[
+                DigestResult::Urc(b"AT+UP_CNT=?"),
+                DigestResult::Response(Ok(b"+UP_CNT: 3")),
+                DigestResult::Response(Ok(b"+DOWN_CNT: 1")),
+                DigestResult::Response(Ok(b"+ATE: OFF")),
+                DigestResult::Urc(b"AT"),
+                DigestResult::Response(Ok(b"OK")),
+            ]
+        );
+        assert_eq!(consumed, input.len());
+
+        let state = DigesterState::new();
+        let mut digester = MokoDigester::new(&state);
+        assert_eq!(digester.digest(b"AT+UP"), (DigestResult::None, 0));
+    }
+
+    #[test]
+    fn error_codes() {
+        assert_eq!(MokoDigester::parse_error_code(b"ERROR(-3)"), Some(-3));
+        assert_eq!(MokoDigester::parse_error_code(b"ERROR (-12)"), Some(-12));
+        assert_eq!(MokoDigester::parse_error_code(b"ERROR"), None);
+        assert_eq!(MokoDigester::parse_error_code(b"ERROR(-x)"), None);
+
+        // Every module keeps its own code
+        let (state, other) = (DigesterState::new(), DigesterState::new());
+        MokoDigester::new(&state).digest(b"ERROR(-4)\r\n");
+        assert_eq!(other.take_error_code(), None);
+        assert_eq!(state.take_error_code(), Some(-4));
+    }
+
+    #[test]
+    fn garbage_is_dropped() {
+        let input = b"\x00\xfejunk\r\n+AT: OK\r\n";
+        let (results, consumed) = digest_all(&DigesterState::new(), input);
+        assert_eq!(results.as_slice(), &[DigestResult::Response(Ok(b"OK"))]);
+        assert_eq!(consumed, input.len());
     }
 }
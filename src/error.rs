@@ -0,0 +1,243 @@
+//! # Crate level error
+//!
+//! Every client method returns [Error]. Errors reported by the module itself as `ERROR(-n)` keep
+//! their numeric code as [ModuleError], everything else is passed on from atat.
+
+use crate::digester::MokoDigester;
+use crate::general::responses::ErrorResponse;
+use atat::Error as AtatError;
+#[cfg(feature = "debug")]
+use defmt::Format;
+
+/// Error reported by the module as `ERROR(-n)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleError {
+    /// ERROR(-1)
+    AtCommandError,
+
+    /// ERROR(-2)
+    AtParameterError,
+
+    /// ERROR(-3)
+    Busy,
+
+    /// ERROR(-5)
+    CouldNotJoinTheNetwork,
+
+    /// ERROR(-7)
+    Timeout,
+
+    /// Any other ERROR(-n), holding n (negative)
+    Other(i16),
+}
+
+impl ModuleError {
+    pub fn from_code(code: i16) -> Self {
+        match code {
+            -1 => ModuleError::AtCommandError,
+            -2 => ModuleError::AtParameterError,
+            -3 => ModuleError::Busy,
+            -5 => ModuleError::CouldNotJoinTheNetwork,
+            -7 => ModuleError::Timeout,
+            code => ModuleError::Other(code),
+        }
+    }
+
+    /// The numeric code as reported by the module, e.g. -3 for [ModuleError::Busy]
+    pub fn code(&self) -> i16 {
+        match self {
+            ModuleError::AtCommandError => -1,
+            ModuleError::AtParameterError => -2,
+            ModuleError::Busy => -3,
+            ModuleError::CouldNotJoinTheNetwork => -5,
+            ModuleError::Timeout => -7,
+            ModuleError::Other(code) => *code,
+        }
+    }
+}
+
+/// A response or command could not be post-processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The response contains bytes that are not valid UTF-8
+    NotUtf8,
+
+    /// The response does not have the expected fields
+    InvalidFormat,
+
+    /// The serialized command does not fit its buffer
+    Overflow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The module answered with ERROR(-n)
+    Module(ModuleError),
+
+    /// An argument can not be turned into a command
+    InvalidArgument,
+
+    /// A response could not be post-processed
+    Parse(ParseError),
+
+    /// The payload is larger than allowed at the current region and data rate
+    PayloadTooLarge { max: u16, actual: usize },
+
+    /// Waiting on a URC without a URC source set on the client
+    NoUrcSource,
+
+    /// The module did not report what the client waited for in time
+    Timeout,
+
+    /// The module asked to hold off uplinks with `NEXT TX after(s)`, for another `retry_after`
+    /// milliseconds
+    DutyCycleRestricted { retry_after: u32 },
+
+    /// Transport or parse error from atat
+    Atat(AtatError),
+}
+
+impl Error {
+    pub fn module_error(&self) -> Option<ModuleError> {
+        match self {
+            Error::Module(e) => Some(*e),
+            _ => None,
+        }
+    }
+
+    /// The numeric module error code, if the module reported one
+    pub fn code(&self) -> Option<i16> {
+        self.module_error().map(|e| e.code())
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.module_error() == Some(ModuleError::Busy)
+    }
+}
+
+impl From<ModuleError> for Error {
+    fn from(value: ModuleError) -> Self {
+        Error::Module(value)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        Error::Parse(value)
+    }
+}
+
+impl Error {
+    /// atat drops the payload of custom errors, so `code` is what the [MokoDigester] recorded when
+    /// it matched the `ERROR(-n)` line, see
+    /// [DigesterState::take_error_code](crate::digester::DigesterState::take_error_code).
+    pub(crate) fn from_atat(value: AtatError, code: Option<i16>) -> Self {
+        match (value, code) {
+            (AtatError::Custom, Some(code)) => Error::Module(ModuleError::from_code(code)),
+            (value, _) => Error::Atat(value),
+        }
+    }
+}
+
+impl From<AtatError> for Error {
+    fn from(value: AtatError) -> Self {
+        Error::Atat(value)
+    }
+}
+
+impl From<ErrorResponse> for Error {
+    fn from(value: ErrorResponse) -> Self {
+        match MokoDigester::parse_error_code(value.error.as_bytes()) {
+            Some(code) => Error::Module(ModuleError::from_code(code)),
+            None => Error::Atat(AtatError::Error),
+        }
+    }
+}
+
+#[cfg(feature = "debug")]
+impl Format for ModuleError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            ModuleError::AtCommandError => defmt::write!(f, "AtCommandError"),
+            ModuleError::AtParameterError => defmt::write!(f, "AtParameterError"),
+            ModuleError::Busy => defmt::write!(f, "Busy"),
+            ModuleError::CouldNotJoinTheNetwork => defmt::write!(f, "CouldNotJoinTheNetwork"),
+            ModuleError::Timeout => defmt::write!(f, "Timeout"),
+            ModuleError::Other(code) => defmt::write!(f, "Other({})", code),
+        }
+    }
+}
+
+#[cfg(feature = "debug")]
+impl Format for ParseError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            ParseError::NotUtf8 => defmt::write!(f, "NotUtf8"),
+            ParseError::InvalidFormat => defmt::write!(f, "InvalidFormat"),
+            ParseError::Overflow => defmt::write!(f, "Overflow"),
+        }
+    }
+}
+
+#[cfg(feature = "debug")]
+impl Format for Error {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Error::Module(e) => defmt::write!(f, "Module({})", e),
+            Error::InvalidArgument => defmt::write!(f, "InvalidArgument"),
+            Error::Parse(e) => defmt::write!(f, "Parse({})", e),
+            Error::PayloadTooLarge { max, actual } => {
+                defmt::write!(f, "PayloadTooLarge(max: {}, actual: {})", max, actual)
+            }
+            Error::NoUrcSource => defmt::write!(f, "NoUrcSource"),
+            Error::Timeout => defmt::write!(f, "Timeout"),
+            Error::DutyCycleRestricted { retry_after } => {
+                defmt::write!(f, "DutyCycleRestricted(retry_after: {})", retry_after)
+            }
+            Error::Atat(e) => defmt::write!(f, "Atat({})", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, ModuleError};
+    use crate::general::responses::ErrorResponse;
+    use atat::Error as AtatError;
+
+    #[test]
+    fn module_error_codes_round_trip() {
+        for code in [-1, -2, -3, -5, -7, -4, -12] {
+            assert_eq!(ModuleError::from_code(code).code(), code);
+        }
+        assert_eq!(ModuleError::from_code(-3), ModuleError::Busy);
+        assert_eq!(ModuleError::from_code(-4), ModuleError::Other(-4));
+    }
+
+    #[test]
+    fn error_response_keeps_code() {
+        let e: Error = ErrorResponse {
+            error: "ERROR(-3)".into(),
+        }
+        .into();
+        assert!(e.is_busy());
+        let e: Error = ErrorResponse {
+            error: "ERROR (-9)".into(),
+        }
+        .into();
+        assert_eq!(e.code(), Some(-9));
+    }
+
+    #[test]
+    fn atat_error_takes_recorded_code() {
+        assert!(Error::from_atat(AtatError::Custom, Some(-3)).is_busy());
+        assert_eq!(
+            Error::from_atat(AtatError::Custom, None),
+            Error::Atat(AtatError::Custom)
+        );
+        assert_eq!(
+            Error::from_atat(AtatError::Timeout, Some(-3)),
+            Error::Atat(AtatError::Timeout)
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use atat_derive::AtatCmd;
 use heapless::String;
 
+#[cfg(feature = "unverified-commands")]
+use super::responses::{
+    BaudRateResponse, FirmwareVersionResponse, LoraVersionResponse, ModelResponse,
+};
 use super::responses::{OkResponse, OnOff};
 
 /// 4.1.1 AT - Verify COM is working
@@ -8,6 +12,30 @@ use super::responses::{OkResponse, OnOff};
 #[at_cmd("AT", OkResponse, cmd_prefix = "", timeout_ms = 5000)]
 pub struct VerifyComIsWorking {}
 
+/// 4.1.1 AT with a short timeout. The module drops the first command after sleeping, this one
+/// is sent to wake it up. The drop and the timeout are unverified, see the README.
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("AT", OkResponse, cmd_prefix = "", timeout_ms = 200)]
+pub struct Wake {}
+
+/// Get firmware version. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+VER=?", FirmwareVersionResponse)]
+pub struct FirmwareVersionGet {}
+
+/// Get LoRaWAN stack version. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+LORA_VER=?", LoraVersionResponse)]
+pub struct LoraVersionGet {}
+
+/// Get module model. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+MODEL=?", ModelResponse)]
+pub struct ModelGet {}
+
 /// 4.1.3 Get ATE - Echo is on/off
 #[derive(Clone, Debug, AtatCmd)]
 #[at_cmd("+ATE=?", OnOff)]
@@ -33,6 +61,21 @@ impl AteSet {
     }
 }
 
+/// Get UART baud rate. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+BAUD=?", BaudRateResponse)]
+pub struct BaudRateGet {}
+
+/// Set UART baud rate. The module answers at the old rate, then switches. Unverified, see the
+/// README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+BAUD", BaudRateResponse, timeout_ms = 4000)]
+pub struct BaudRateSet {
+    pub baud_rate: u32,
+}
+
 /// 4.1.5 Get Sleep status
 #[derive(Clone, Debug, AtatCmd)]
 #[at_cmd("+SLEEP=?", OnOff)]
@@ -65,7 +108,7 @@ pub struct Reset {}
 
 #[cfg(test)]
 mod tests {
-    use crate::general::commands::{AteGet, AteSet, SleepSet, VerifyComIsWorking};
+    use crate::general::commands::{AteGet, AteSet, SleepSet, VerifyComIsWorking, Wake};
     use atat::AtatCmd;
 
     #[test]
@@ -75,6 +118,12 @@ mod tests {
         assert_eq!(k, b"AT\r\n");
     }
 
+    #[test]
+    fn wake() {
+        let k = Wake {}.as_bytes();
+        assert_eq!(k, b"AT\r\n");
+    }
+
     #[test]
     fn ate_get() {
         let k = AteGet {}.as_bytes();
@@ -96,4 +145,31 @@ mod tests {
         let k = SleepSet::off().as_bytes();
         assert_eq!(k, b"AT+SLEEP=OFF\r\n");
     }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn baud_rate() {
+        use crate::general::commands::{BaudRateGet, BaudRateSet};
+        use crate::general::types::BaudRate;
+
+        let k = BaudRateGet {}.as_bytes();
+        assert_eq!(k, b"AT+BAUD=?\r\n");
+        let k = BaudRate::B115200.set_cmd().as_bytes();
+        assert_eq!(k, b"AT+BAUD=115200\r\n");
+        let k = BaudRateSet { baud_rate: 9600 }.as_bytes();
+        assert_eq!(k, b"AT+BAUD=9600\r\n");
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn versions() {
+        use crate::general::commands::{FirmwareVersionGet, LoraVersionGet, ModelGet};
+
+        let k = FirmwareVersionGet {}.as_bytes();
+        assert_eq!(k, b"AT+VER=?\r\n");
+        let k = LoraVersionGet {}.as_bytes();
+        assert_eq!(k, b"AT+LORA_VER=?\r\n");
+        let k = ModelGet {}.as_bytes();
+        assert_eq!(k, b"AT+MODEL=?\r\n");
+    }
 }
@@ -1,50 +1,419 @@
 pub mod commands;
 pub mod responses;
+pub(crate) mod session;
 pub mod types;
 
 #[cfg(feature = "async")]
 pub mod asynch {
-    use crate::client::asynch::MokoMkl62BaClient;
-    use crate::general::commands::{AteGet, AteSet, Reset, SleepGet, SleepSet, VerifyComIsWorking};
+    use crate::client::asynch::{MokoMkl62BaClient, URC_POLL_INTERVAL};
+    use crate::error::Error;
+    use crate::general::commands::{
+        AteGet, AteSet, Reset, SleepGet, SleepSet, VerifyComIsWorking, Wake,
+    };
+    #[cfg(feature = "unverified-commands")]
+    use crate::general::commands::{BaudRateGet, FirmwareVersionGet, LoraVersionGet, ModelGet};
+    use crate::general::types::BootBanner;
+    #[cfg(feature = "unverified-commands")]
+    use crate::general::types::{BaudRate, FirmwareVersion, ModuleVersion};
     use atat::asynch::AtatClient;
-    use atat::Error;
+    #[cfg(feature = "debug")]
+    use defmt::error;
+    use embassy_time::{Duration, Instant, Timer};
     use embedded_io::asynch::Write;
+    #[cfg(feature = "unverified-commands")]
+    use heapless::String;
+
+    /// Time the module takes to switch to a new baud rate after answering +BAUD
+    #[cfg(feature = "unverified-commands")]
+    const BAUD_SWITCH_DELAY: Duration = Duration::from_millis(100);
+
+    /// `AT` attempts at the new baud rate before giving up
+    #[cfg(feature = "unverified-commands")]
+    const BAUD_VERIFY_ATTEMPTS: u8 = 3;
+
+    /// `AT` attempts after the throw away one before giving up on waking the module
+    const WAKE_ATTEMPTS: u8 = 3;
+
+    /// RX1 delay the module starts with, used while it has not been read
+    const DEFAULT_RX1_DELAY: u8 = 1;
+
+    /// Time a downlink in RX2 can take once the window opened. A full downlink at SF12 is on air
+    /// for close to 3 s.
+    const RX2_RECEIVE_TIME: Duration = Duration::from_secs(3);
 
     impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> MokoMkl62BaClient<'a, W, INGRESS_BUF_SIZE> {
         pub async fn verify_com_is_working(&mut self) -> Result<bool, Error> {
             let command = VerifyComIsWorking {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command).await?;
             Ok(response.is_ok())
         }
 
         pub async fn at_echo_on(&mut self) -> Result<bool, Error> {
             let command = AteGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command).await?;
             Ok(response.is_on())
         }
 
         pub async fn at_echo_set(&mut self, on: bool) -> Result<bool, Error> {
             let command = if on { AteSet::on() } else { AteSet::off() };
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command).await?;
+            self.session.echo = Some(response.is_on());
             Ok(response.is_on())
         }
 
         pub async fn sleep_status(&mut self) -> Result<bool, Error> {
             let command = SleepGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command).await?;
             Ok(response.is_on())
         }
 
         pub async fn sleep_set(&mut self, on: bool) -> Result<bool, Error> {
             let command = if on { SleepSet::on() } else { SleepSet::off() };
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command).await?;
+            self.session.sleep = Some(response.is_on());
             Ok(response.is_on())
         }
 
+        /// Put the module to sleep. It is woken up again before the next command.
+        pub async fn sleep(&mut self) -> Result<(), Error> {
+            match self.sleep_set(true).await? {
+                true => Ok(()),
+                false => Err(Error::InvalidArgument),
+            }
+        }
+
+        /// Wake the module up. It drops the first command after sleeping, so a throw away `AT`
+        /// goes first, after which it has to answer `AT` again. Done before any command while
+        /// the module is asleep. Unverified, see the README.
+        pub async fn wake(&mut self) -> Result<(), Error> {
+            self.client.send(&Wake {}).await.ok();
+            for _ in 0..WAKE_ATTEMPTS {
+                if let Ok(response) = self.client.send(&Wake {}).await {
+                    if response.is_ok() {
+                        self.session.sleep = Some(false);
+                        return Ok(());
+                    }
+                }
+            }
+            Err(Error::Timeout)
+        }
+
+        /// Put to sleep and not woken up since, as far as the client knows
+        pub fn is_asleep(&mut self) -> bool {
+            self.poll_urcs();
+            self.session.sleep == Some(true)
+        }
+
+        /// Called after an uplink, sleeps once the RX windows have closed if auto sleep is on.
+        /// RX2 opens a second after RX1, and is not opened at all after a downlink in RX1.
+        pub(crate) async fn auto_sleep(&mut self) {
+            if !self.session.auto_sleep {
+                return;
+            }
+            let rx1_delay = self.session.rx1_delay.unwrap_or(DEFAULT_RX1_DELAY);
+            let deadline =
+                Instant::now() + Duration::from_secs(rx1_delay as u64 + 1) + RX2_RECEIVE_TIME;
+            loop {
+                self.poll_urcs();
+                if self.session.downlink_received || Instant::now() >= deadline {
+                    break;
+                }
+                Timer::after(URC_POLL_INTERVAL).await;
+            }
+            if self.sleep().await.is_err() {
+                #[cfg(feature = "debug")]
+                error!("Error putting Moko to sleep after the uplink");
+            }
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub async fn firmware_version(&mut self) -> Result<FirmwareVersion, Error> {
+            let command = FirmwareVersionGet {};
+            let response = self.command(&command).await?;
+            Ok(response.version()?)
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub async fn lora_version(&mut self) -> Result<u32, Error> {
+            let command = LoraVersionGet {};
+            let response = self.command(&command).await?;
+            Ok(response.lora_version)
+        }
+
+        /// Model identifier, e.g. `MKL62BA`
+        #[cfg(feature = "unverified-commands")]
+        pub async fn model(&mut self) -> Result<String<16>, Error> {
+            let command = ModelGet {};
+            let response = self.command(&command).await?;
+            Ok(response.model)
+        }
+
+        /// Firmware and LoRaWAN stack version, without resetting the module for the boot banner
+        #[cfg(feature = "unverified-commands")]
+        pub async fn module_version(&mut self) -> Result<ModuleVersion, Error> {
+            Ok(ModuleVersion {
+                firmware: self.firmware_version().await?,
+                lora_version: self.lora_version().await?,
+            })
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub async fn baud_rate(&mut self) -> Result<BaudRate, Error> {
+            let command = BaudRateGet {};
+            let response = self.command(&command).await?;
+            Ok(BaudRate::try_from(response.baud_rate)?)
+        }
+
+        /// Switch the module UART to `baud_rate`. Once the module has answered at the old rate
+        /// and had time to switch, `reconfigure` is called to move the host UART to the new rate.
+        /// The module is then expected to answer `AT`, otherwise [Error::Timeout] is returned and
+        /// both sides are probably out of step.
+        #[cfg(feature = "unverified-commands")]
+        pub async fn baud_rate_set<F: FnOnce(BaudRate)>(
+            &mut self,
+            baud_rate: BaudRate,
+            reconfigure: F,
+        ) -> Result<(), Error> {
+            self.command(&baud_rate.set_cmd()).await?;
+            Timer::after(BAUD_SWITCH_DELAY).await;
+            reconfigure(baud_rate);
+            for _ in 0..BAUD_VERIFY_ATTEMPTS {
+                if let Ok(true) = self.verify_com_is_working().await {
+                    return Ok(());
+                }
+                Timer::after(BAUD_SWITCH_DELAY).await;
+            }
+            Err(Error::Timeout)
+        }
+
+        /// Reset the module. The boot banner that follows is collected by
+        /// [wait_for_boot](Self::wait_for_boot).
         pub async fn reset(&mut self) -> Result<(), Error> {
+            self.poll_urcs();
+            self.boot = BootBanner::default();
+            self.session.reset_requested();
             let command = Reset {};
-            self.client.send(&command).await?;
+            self.command(&command).await?;
             Ok(())
         }
     }
 }
+
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use crate::client::blocking::MokoMkl62BaBlockingClient;
+    use crate::error::Error;
+    use crate::general::commands::{
+        AteGet, AteSet, Reset, SleepGet, SleepSet, VerifyComIsWorking, Wake,
+    };
+    #[cfg(feature = "unverified-commands")]
+    use crate::general::commands::{BaudRateGet, FirmwareVersionGet, LoraVersionGet, ModelGet};
+    #[cfg(feature = "unverified-commands")]
+    use crate::general::types::{BaudRate, FirmwareVersion, ModuleVersion};
+    use atat::blocking::AtatClient;
+    use embedded_io::blocking::Write;
+    #[cfg(feature = "unverified-commands")]
+    use heapless::String;
+
+    /// `AT` attempts after the throw away one before giving up on waking the module
+    const WAKE_ATTEMPTS: u8 = 3;
+
+    impl<'a, W: Write, const INGRESS_BUF_SIZE: usize>
+        MokoMkl62BaBlockingClient<'a, W, INGRESS_BUF_SIZE>
+    {
+        pub fn verify_com_is_working(&mut self) -> Result<bool, Error> {
+            let command = VerifyComIsWorking {};
+            let response = self.command(&command)?;
+            Ok(response.is_ok())
+        }
+
+        pub fn at_echo_on(&mut self) -> Result<bool, Error> {
+            let command = AteGet {};
+            let response = self.command(&command)?;
+            Ok(response.is_on())
+        }
+
+        pub fn at_echo_set(&mut self, on: bool) -> Result<bool, Error> {
+            let command = if on { AteSet::on() } else { AteSet::off() };
+            let response = self.command(&command)?;
+            Ok(response.is_on())
+        }
+
+        pub fn sleep_status(&mut self) -> Result<bool, Error> {
+            let command = SleepGet {};
+            let response = self.command(&command)?;
+            Ok(response.is_on())
+        }
+
+        pub fn sleep_set(&mut self, on: bool) -> Result<bool, Error> {
+            let command = if on { SleepSet::on() } else { SleepSet::off() };
+            let response = self.command(&command)?;
+            self.session.sleep = Some(response.is_on());
+            Ok(response.is_on())
+        }
+
+        /// Put the module to sleep. It is woken up again before the next command. There is no
+        /// auto sleep, the blocking client leaves waiting out the RX windows to the application.
+        pub fn sleep(&mut self) -> Result<(), Error> {
+            match self.sleep_set(true)? {
+                true => Ok(()),
+                false => Err(Error::InvalidArgument),
+            }
+        }
+
+        /// Wake the module up. It drops the first command after sleeping, so a throw away `AT`
+        /// goes first, after which it has to answer `AT` again. Done before any command while
+        /// the module is asleep. Unverified, see the README.
+        pub fn wake(&mut self) -> Result<(), Error> {
+            self.client.send(&Wake {}).ok();
+            for _ in 0..WAKE_ATTEMPTS {
+                if let Ok(response) = self.client.send(&Wake {}) {
+                    if response.is_ok() {
+                        self.session.sleep = Some(false);
+                        return Ok(());
+                    }
+                }
+            }
+            Err(Error::Timeout)
+        }
+
+        /// Put to sleep and not woken up since, as far as the client knows
+        pub fn is_asleep(&self) -> bool {
+            self.session.sleep == Some(true)
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub fn firmware_version(&mut self) -> Result<FirmwareVersion, Error> {
+            let command = FirmwareVersionGet {};
+            let response = self.command(&command)?;
+            Ok(response.version()?)
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub fn lora_version(&mut self) -> Result<u32, Error> {
+            let command = LoraVersionGet {};
+            let response = self.command(&command)?;
+            Ok(response.lora_version)
+        }
+
+        /// Model identifier, e.g. `MKL62BA`
+        #[cfg(feature = "unverified-commands")]
+        pub fn model(&mut self) -> Result<String<16>, Error> {
+            let command = ModelGet {};
+            let response = self.command(&command)?;
+            Ok(response.model)
+        }
+
+        /// Firmware and LoRaWAN stack version, without resetting the module for the boot banner
+        #[cfg(feature = "unverified-commands")]
+        pub fn module_version(&mut self) -> Result<ModuleVersion, Error> {
+            Ok(ModuleVersion {
+                firmware: self.firmware_version()?,
+                lora_version: self.lora_version()?,
+            })
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub fn baud_rate(&mut self) -> Result<BaudRate, Error> {
+            let command = BaudRateGet {};
+            let response = self.command(&command)?;
+            Ok(BaudRate::try_from(response.baud_rate)?)
+        }
+
+        /// Switch the module UART to `baud_rate`. `reconfigure` is called once the module has
+        /// answered at the old rate, it has to give the module time to switch and move the host
+        /// UART to the new rate. The module is then expected to answer `AT`.
+        #[cfg(feature = "unverified-commands")]
+        pub fn baud_rate_set<F: FnOnce(BaudRate)>(
+            &mut self,
+            baud_rate: BaudRate,
+            reconfigure: F,
+        ) -> Result<(), Error> {
+            self.command(&baud_rate.set_cmd())?;
+            reconfigure(baud_rate);
+            match self.verify_com_is_working() {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(Error::Timeout),
+                Err(e) => Err(e),
+            }
+        }
+
+        pub fn reset(&mut self) -> Result<(), Error> {
+            let command = Reset {};
+            self.command(&command)?;
+            // Modules boot awake
+            self.session.sleep = Some(false);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "async"))]
+mod tests {
+    use crate::sim::{run_client, Mkl62baSim};
+    use embassy_time::{Duration, Instant};
+
+    #[test]
+    fn sleep_and_wake() {
+        let sim = Mkl62baSim::new();
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, sim| async move {
+                client.sleep().await.unwrap();
+                assert!(client.is_asleep());
+                assert!(sim.with_state(|s| s.sleep));
+                // Woken up by the next command, which the module would otherwise drop
+                assert!(client.verify_com_is_working().await.unwrap());
+                assert!(!client.is_asleep());
+                assert!(!sim.with_state(|s| s.sleep));
+            },
+        );
+    }
+
+    #[test]
+    fn auto_sleep() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| {
+            s.joined = true;
+            #[cfg(feature = "unverified-commands")]
+            s.rx1_delay = 5;
+            s.confirm = true;
+            s.downlink_urcs = true;
+        });
+        run_client(
+            &sim,
+            |builder| builder.skip_reset().auto_sleep(true),
+            |mut client, sim| async move {
+                // The ACK ends the wait for the RX windows, 5 s with the default RX1 delay and 9 s
+                // with the one read from the module
+                let start = Instant::now();
+                client.send(0, 8, b"Hello").await.unwrap();
+                assert!(start.elapsed() < Duration::from_secs(2));
+                assert!(client.is_asleep());
+                assert!(sim.with_state(|s| s.sleep));
+            },
+        );
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn auto_sleep_rx1_delay_refused() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| {
+            s.joined = true;
+            s.confirm = true;
+            s.downlink_urcs = true;
+        });
+        sim.fail_command("+RX1_DELAY", -1);
+        run_client(
+            &sim,
+            |builder| builder.skip_reset().auto_sleep(true),
+            |mut client, sim| async move {
+                client.send(0, 8, b"Hello").await.unwrap();
+                assert_eq!(sim.with_state(|s| s.uplinks.len()), 1);
+                assert!(client.is_asleep());
+            },
+        );
+    }
+}
@@ -1,8 +1,9 @@
+#[cfg(feature = "unverified-commands")]
+use crate::error::ParseError;
+#[cfg(feature = "unverified-commands")]
+use crate::general::types::FirmwareVersion;
 use atat::serde_at::HexStr;
-use atat::Error as AtatError;
 use atat_derive::AtatResp;
-#[cfg(feature = "debug")]
-use defmt::Format;
 use heapless::String;
 
 /// OK response
@@ -32,85 +33,45 @@ impl OnOff {
     }
 }
 
+/// Firmware version, e.g. `1.0.2`
+#[cfg(feature = "unverified-commands")]
 #[derive(Debug, Clone, AtatResp, PartialEq)]
-pub struct ErrorResponse {
-    pub error: String<20>,
+pub struct FirmwareVersionResponse {
+    pub version: String<16>,
 }
 
-pub enum Error {
-    /// ERROR (-1)
-    AtCommandError,
-
-    /// ERROR (-2)
-    AtParameterError,
-
-    /// ERROR (-3)
-    Busy,
-
-    /// ERROR (-5)
-    CouldNotJoinTheNetwork,
-
-    /// ERROR (-7)
-    Timeout,
-    Unknown,
+#[cfg(feature = "unverified-commands")]
+impl FirmwareVersionResponse {
+    pub fn version(&self) -> Result<FirmwareVersion, ParseError> {
+        self.version.parse()
+    }
 }
 
-impl From<ErrorResponse> for Error {
-    fn from(value: ErrorResponse) -> Self {
-        match value.error.as_str() {
-            "ERROR (-1)" => Error::AtCommandError,
-            "ERROR (-2)" => Error::AtParameterError,
-            "ERROR (-3)" => Error::Busy,
-            "ERROR (-5)" => Error::CouldNotJoinTheNetwork,
-            "ERROR (-7)" => Error::Timeout,
-            _ => Error::Unknown,
-        }
-    }
+/// LoRaWAN stack version, as in the `LORA VERSION` boot banner line
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct LoraVersionResponse {
+    pub lora_version: u32,
 }
 
-impl From<ErrorResponse> for AtatError {
-    fn from(value: ErrorResponse) -> Self {
-        match value.error.as_str() {
-            "ERROR (-1)" => AtatError::Error,
-            "ERROR (-2)" => AtatError::Parse,
-            "ERROR (-3)" => AtatError::Read,
-            "ERROR (-5)" => AtatError::Error,
-            "ERROR (-7)" => AtatError::Timeout,
-            _ => AtatError::Error,
-        }
-    }
+/// Module model, e.g. `MKL62BA`
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct ModelResponse {
+    pub model: String<16>,
 }
 
-impl From<AtatError> for Error {
-    fn from(value: AtatError) -> Self {
-        match value {
-            AtatError::Read => Self::AtCommandError,
-            AtatError::Write => Self::AtCommandError,
-            AtatError::Timeout => Self::Timeout,
-            AtatError::InvalidResponse => Self::AtCommandError,
-            AtatError::Aborted => Self::AtCommandError,
-            // AtatError::Read => Self::AtCommandError,
-            AtatError::Parse => Self::AtParameterError,
-            AtatError::Error => Self::AtParameterError,
-            AtatError::CmeError(_) => Self::Unknown,
-            AtatError::CmsError(_) => Self::Unknown,
-            AtatError::ConnectionError(_) => Self::CouldNotJoinTheNetwork,
-            AtatError::Custom => Self::Unknown,
-        }
-    }
+/// UART baud rate
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct BaudRateResponse {
+    pub baud_rate: u32,
 }
-#[cfg(feature = "debug")]
-impl Format for Error {
-    fn format(&self, f: defmt::Formatter) {
-        match self {
-            Error::AtCommandError => defmt::write!(f, "AtCommandError"),
-            Error::AtParameterError => defmt::write!(f, "AtParameterError"),
-            Error::Busy => defmt::write!(f, "Busy"),
-            Error::CouldNotJoinTheNetwork => defmt::write!(f, "CouldNotJoinTheNetwork"),
-            Error::Timeout => defmt::write!(f, "Timeout"),
-            Error::Unknown => defmt::write!(f, "Unknown"),
-        }
-    }
+
+/// ERROR(-n) response, see [Error](crate::error::Error) for turning it into an error
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct ErrorResponse {
+    pub error: String<20>,
 }
 
 /// Get AppEUI response
@@ -143,4 +104,21 @@ mod tests {
         };
         assert!(k.is_off());
     }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn firmware_version() {
+        use crate::error::ParseError;
+        use crate::general::responses::FirmwareVersionResponse;
+        use crate::general::types::FirmwareVersion;
+
+        let v = FirmwareVersionResponse {
+            version: heapless::String::from("1.0.2"),
+        };
+        assert_eq!(v.version(), Ok(FirmwareVersion::new(1, 0, 2)));
+        let v = FirmwareVersionResponse {
+            version: heapless::String::from("MKL62BA"),
+        };
+        assert_eq!(v.version(), Err(ParseError::InvalidFormat));
+    }
 }
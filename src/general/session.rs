@@ -0,0 +1,167 @@
+use crate::general::types::ResetReason;
+use crate::lora::config::LoraConfig;
+use crate::lora::types::{JoinPolicy, OtaaCredentials};
+
+/// What the client set up on the module, so it can be restored after an unexpected reset.
+///
+/// A `SYSTEM START` that was not asked for with +RESET means the module rebooted on its own,
+/// e.g. on a brown out. It comes back with echo on, without a network session and possibly
+/// without settings that were not saved.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Session {
+    pub(crate) joined: bool,
+    pub(crate) echo: Option<bool>,
+    /// Whether the module is asleep, as far as the client knows
+    pub(crate) sleep: Option<bool>,
+    /// Put the module back to sleep after each uplink
+    pub(crate) auto_sleep: bool,
+    /// RX1 delay in seconds as last read or written. Dropped on reset and join, as the module
+    /// falls back to its default and the network can change it in the join accept.
+    pub(crate) rx1_delay: Option<u8>,
+    /// A downlink or ACK was reported since the last uplink, so the RX windows are over
+    pub(crate) downlink_received: bool,
+    /// Every setting applied with `apply_config`, latest value wins
+    pub(crate) config: Option<LoraConfig>,
+    /// Credentials and policy of the last OTAA join
+    pub(crate) otaa: Option<(OtaaCredentials, JoinPolicy)>,
+    /// Join again when recovering, if the module was joined before the reset
+    pub(crate) rejoin: bool,
+    pub(crate) resets: u32,
+    pub(crate) last_reset: Option<ResetReason>,
+    reset_requested: bool,
+    recovery_pending: bool,
+    rejoin_pending: bool,
+    restore_sleep: bool,
+}
+
+impl Session {
+    /// +RESET is about to be sent
+    pub(crate) fn reset_requested(&mut self) {
+        self.reset_requested = true;
+    }
+
+    pub(crate) fn system_start(&mut self) {
+        self.resets = self.resets.wrapping_add(1);
+        let reason = if core::mem::take(&mut self.reset_requested) {
+            ResetReason::Requested
+        } else {
+            self.recovery_pending = true;
+            self.rejoin_pending |= self.joined;
+            self.restore_sleep |= self.sleep == Some(true);
+            ResetReason::Unexpected
+        };
+        self.joined = false;
+        self.rx1_delay = None;
+        // Modules boot awake
+        self.sleep = Some(false);
+        self.last_reset = Some(reason);
+    }
+
+    pub(crate) fn config_applied(&mut self, config: &LoraConfig) {
+        match &mut self.config {
+            Some(applied) => applied.merge(config),
+            None => self.config = Some(config.clone()),
+        }
+    }
+
+    /// An unexpected reset still has to be recovered from, cleared on read
+    pub(crate) fn take_recovery(&mut self) -> bool {
+        core::mem::take(&mut self.recovery_pending)
+    }
+
+    /// Try again on the next call of `recover_if_reset`
+    pub(crate) fn recovery_failed(&mut self) {
+        self.recovery_pending = true;
+    }
+
+    /// The module was asleep when it reset on its own, cleared on read
+    pub(crate) fn take_restore_sleep(&mut self) -> bool {
+        core::mem::take(&mut self.restore_sleep)
+    }
+
+    /// Credentials to join with, if the module lost a session it should get back
+    pub(crate) fn take_rejoin(&mut self) -> Option<(OtaaCredentials, JoinPolicy)> {
+        if !core::mem::take(&mut self.rejoin_pending) || !self.rejoin {
+            return None;
+        }
+        self.otaa.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::general::session::Session;
+    use crate::general::types::ResetReason;
+    use crate::lora::config::LoraConfig;
+    use crate::lora::types::{JoinPolicy, LoraClass, LoraRegion, OtaaCredentials};
+
+    #[test]
+    fn requested_reset() {
+        let mut session = Session::default();
+        session.joined = true;
+        session.reset_requested();
+        session.system_start();
+        assert_eq!(session.resets, 1);
+        assert_eq!(session.last_reset, Some(ResetReason::Requested));
+        assert!(!session.joined);
+        assert!(!session.take_recovery());
+    }
+
+    #[test]
+    fn unexpected_reset() {
+        let credentials = OtaaCredentials {
+            dev_eui: 1,
+            app_eui: 2,
+            app_key: 3,
+        };
+        let mut session = Session {
+            joined: true,
+            sleep: Some(true),
+            rx1_delay: Some(5),
+            rejoin: true,
+            otaa: Some((credentials.clone(), JoinPolicy::default())),
+            ..Default::default()
+        };
+        session.system_start();
+        assert_eq!(session.last_reset, Some(ResetReason::Unexpected));
+        assert!(!session.joined);
+        assert_eq!(session.rx1_delay, None);
+        assert!(session.take_recovery());
+        assert!(!session.take_recovery());
+        assert_eq!(session.sleep, Some(false));
+        assert!(session.take_restore_sleep());
+        assert!(!session.take_restore_sleep());
+        assert_eq!(
+            session.take_rejoin(),
+            Some((credentials, JoinPolicy::default()))
+        );
+        assert_eq!(session.take_rejoin(), None);
+
+        // Not joined before the reset, nothing to rejoin
+        session.system_start();
+        assert_eq!(session.resets, 2);
+        assert_eq!(session.take_rejoin(), None);
+    }
+
+    #[test]
+    fn config_is_merged() {
+        let mut session = Session::default();
+        session.config_applied(&LoraConfig {
+            region: Some(LoraRegion::Eu868),
+            class: Some(LoraClass::ClassA),
+            ..Default::default()
+        });
+        session.config_applied(&LoraConfig {
+            class: Some(LoraClass::ClassC),
+            ..Default::default()
+        });
+        assert_eq!(
+            session.config,
+            Some(LoraConfig {
+                region: Some(LoraRegion::Eu868),
+                class: Some(LoraClass::ClassC),
+                ..Default::default()
+            })
+        );
+    }
+}
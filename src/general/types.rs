@@ -1 +1,286 @@
+use crate::error::ParseError;
+#[cfg(feature = "unverified-commands")]
+use crate::general::commands::BaudRateSet;
+use crate::lora::types::LoraRegion;
+use crate::urc::URCMessages;
+use core::str::FromStr;
 
+/// Software version of the module, `SOFT VERSION:major.minor.patch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl FirmwareVersion {
+    pub const fn new(major: u8, minor: u8, patch: u8) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl FromStr for FirmwareVersion {
+    type Err = ParseError;
+
+    /// Parses `1.0.2`, with or without a leading `V`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let value = value
+            .strip_prefix('V')
+            .or_else(|| value.strip_prefix('v'))
+            .unwrap_or(value);
+        let mut parts = value.split('.');
+        let mut next = || -> Result<u8, ParseError> {
+            parts
+                .next()
+                .ok_or(ParseError::InvalidFormat)?
+                .parse()
+                .map_err(|_| ParseError::InvalidFormat)
+        };
+        let version = FirmwareVersion::new(next()?, next()?, next()?);
+        if parts.next().is_some() {
+            return Err(ParseError::InvalidFormat);
+        }
+        Ok(version)
+    }
+}
+
+/// Firmware and LoRaWAN stack version of the module. Ordered by firmware version first, so
+/// features can be gated with e.g. `version.firmware >= FirmwareVersion::new(1, 1, 0)`.
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModuleVersion {
+    pub firmware: FirmwareVersion,
+    pub lora_version: u32,
+}
+
+/// UART baud rates the module supports. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum BaudRate {
+    B1200,
+    B2400,
+    B4800,
+    #[default]
+    B9600,
+    B19200,
+    B38400,
+    B57600,
+    B115200,
+}
+
+#[cfg(feature = "unverified-commands")]
+impl BaudRate {
+    pub fn as_u32(self) -> u32 {
+        match self {
+            BaudRate::B1200 => 1200,
+            BaudRate::B2400 => 2400,
+            BaudRate::B4800 => 4800,
+            BaudRate::B9600 => 9600,
+            BaudRate::B19200 => 19200,
+            BaudRate::B38400 => 38400,
+            BaudRate::B57600 => 57600,
+            BaudRate::B115200 => 115200,
+        }
+    }
+
+    pub fn set_cmd(self) -> BaudRateSet {
+        BaudRateSet {
+            baud_rate: self.as_u32(),
+        }
+    }
+}
+
+#[cfg(feature = "unverified-commands")]
+impl TryFrom<u32> for BaudRate {
+    type Error = ParseError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            1200 => BaudRate::B1200,
+            2400 => BaudRate::B2400,
+            4800 => BaudRate::B4800,
+            9600 => BaudRate::B9600,
+            19200 => BaudRate::B19200,
+            38400 => BaudRate::B38400,
+            57600 => BaudRate::B57600,
+            115200 => BaudRate::B115200,
+            _ => return Err(ParseError::InvalidFormat),
+        })
+    }
+}
+
+/// Why the module last printed its boot banner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetReason {
+    /// After a +RESET sent by the client
+    Requested,
+    /// The module rebooted on its own, e.g. on a brown out
+    Unexpected,
+}
+
+/// Module details announced in the boot banner
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInfo {
+    pub software_version: FirmwareVersion,
+    pub lora_version: u32,
+    pub region: LoraRegion,
+}
+
+impl ModuleInfo {
+    pub fn version(&self) -> ModuleVersion {
+        ModuleVersion {
+            firmware: self.software_version,
+            lora_version: self.lora_version,
+        }
+    }
+}
+
+/// Collects the boot banner URCs (`SYSTEM START`, `SOFT VERSION`, `LORA VERSION`,
+/// `LORA REGION`) into a [ModuleInfo]
+#[derive(Debug, Clone, Default)]
+pub struct BootBanner {
+    started: bool,
+    software_version: Option<FirmwareVersion>,
+    lora_version: Option<u32>,
+    region: Option<LoraRegion>,
+}
+
+impl BootBanner {
+    /// Feed a URC, returns true if it was part of the banner
+    pub fn handle(&mut self, urc: &URCMessages) -> bool {
+        match urc {
+            URCMessages::SystemStart => {
+                *self = BootBanner {
+                    started: true,
+                    ..Default::default()
+                }
+            }
+            URCMessages::SoftwareVersion(major, minor, patch) => {
+                self.software_version = Some(FirmwareVersion {
+                    major: *major,
+                    minor: *minor,
+                    patch: *patch,
+                })
+            }
+            URCMessages::LoraVersion(version) => self.lora_version = Some(*version),
+            URCMessages::LoraRegion(region) => self.region = Some(region.clone()),
+            _ => return false,
+        }
+        true
+    }
+
+    /// `SYSTEM START` was seen
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// The whole banner was seen
+    pub fn is_complete(&self) -> bool {
+        self.module_info().is_some()
+    }
+
+    pub fn module_info(&self) -> Option<ModuleInfo> {
+        if !self.started {
+            return None;
+        }
+        match (self.software_version, self.lora_version, &self.region) {
+            (Some(software_version), Some(lora_version), Some(region)) => Some(ModuleInfo {
+                software_version,
+                lora_version,
+                region: region.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ParseError;
+    use crate::general::types::{BootBanner, FirmwareVersion, ModuleInfo};
+    use crate::lora::types::LoraRegion;
+    use crate::urc::URCMessages;
+
+    #[test]
+    fn boot_banner() {
+        let mut banner = BootBanner::default();
+        assert!(banner.handle(&URCMessages::SoftwareVersion(1, 0, 0)));
+        assert!(banner.handle(&URCMessages::SystemStart));
+        assert!(banner.is_started());
+        assert!(banner.handle(&URCMessages::SoftwareVersion(1, 2, 3)));
+        assert!(banner.handle(&URCMessages::LoraVersion(104)));
+        assert!(!banner.handle(&URCMessages::NextTxInSeconds(3)));
+        assert!(!banner.is_complete());
+        assert!(banner.handle(&URCMessages::LoraRegion(LoraRegion::Eu868)));
+        assert_eq!(
+            banner.module_info(),
+            Some(ModuleInfo {
+                software_version: FirmwareVersion {
+                    major: 1,
+                    minor: 2,
+                    patch: 3
+                },
+                lora_version: 104,
+                region: LoraRegion::Eu868,
+            })
+        );
+    }
+
+    #[test]
+    fn firmware_version_ordering() {
+        let v = |major, minor, patch| FirmwareVersion {
+            major,
+            minor,
+            patch,
+        };
+        assert!(v(1, 2, 3) < v(1, 3, 0));
+        assert!(v(2, 0, 0) > v(1, 9, 9));
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn baud_rate() {
+        use crate::general::types::BaudRate;
+
+        assert_eq!(BaudRate::default().as_u32(), 9600);
+        assert_eq!(BaudRate::try_from(115200), Ok(BaudRate::B115200));
+        assert_eq!(BaudRate::try_from(9601), Err(ParseError::InvalidFormat));
+        assert!(BaudRate::B1200 < BaudRate::B115200);
+    }
+
+    #[test]
+    fn firmware_version_parse() {
+        assert_eq!("1.0.2".parse(), Ok(FirmwareVersion::new(1, 0, 2)));
+        assert_eq!("V2.10.0".parse(), Ok(FirmwareVersion::new(2, 10, 0)));
+        assert_eq!(
+            "1.0".parse::<FirmwareVersion>(),
+            Err(ParseError::InvalidFormat)
+        );
+        assert_eq!(
+            "1.0.2.3".parse::<FirmwareVersion>(),
+            Err(ParseError::InvalidFormat)
+        );
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn module_version() {
+        use crate::general::types::ModuleVersion;
+
+        let old = ModuleVersion {
+            firmware: FirmwareVersion::new(1, 0, 2),
+            lora_version: 104,
+        };
+        let new = ModuleVersion {
+            firmware: FirmwareVersion::new(1, 1, 0),
+            lora_version: 103,
+        };
+        assert!(old < new);
+        assert!(new.firmware >= FirmwareVersion::new(1, 1, 0));
+    }
+}
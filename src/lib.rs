@@ -1,9 +1,24 @@
+//! AT commands and clients for the Moko MKL62BA LoRaWAN module.
+//!
+//! [MokoMkl62BaClient](client::asynch::MokoMkl62BaClient) (feature `async`) is the full client.
+//! [MokoMkl62BaBlockingClient](client::blocking::MokoMkl62BaBlockingClient) (feature
+//! `blocking`) has the same commands and config handling, but no join driver, duty cycle
+//! tracking, reset recovery, downlink URCs, auto sleep or uplink queue.
+
 #![no_std]
+#![cfg_attr(all(feature = "std", feature = "async"), allow(incomplete_features))]
+#![cfg_attr(all(feature = "std", feature = "async"), feature(async_fn_in_trait))]
+
+#[cfg(feature = "std")]
+extern crate std;
 
 pub mod client;
 pub mod digester;
+pub mod error;
 pub mod general;
 pub mod lora;
+#[cfg(feature = "std")]
+pub mod sim;
 pub mod urc;
 
 use atat_derive::AtatResp;
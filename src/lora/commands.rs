@@ -1,18 +1,26 @@
 use atat::serde_at::HexStr;
 use atat_derive::AtatCmd;
-use core::str::FromStr;
+use core::fmt::Write;
 use heapless::String;
-use serde_at::SerializeOptions;
 
 use super::responses::{
-    AppEuiGet as AppEuiGetVal, AppKeyGet as AppKeyGetVal, DevEuiGet as DevEuiGetVal,
-    DownlinkFrameCountResponse, DrSetResponse, LoraClassGet as LoraClassGetVal, LoraJoinMode,
-    LoraJoinResponse, LoraMaxTxLength, LoraReceivedBytesResponseRaw,
-    LoraRegionGet as LoraRegionGetVal, LoraSendBytesResponseUnprocessed, UplinkFrameCountResponse,
+    AppEuiGet as AppEuiGetVal, AppKeyGet as AppKeyGetVal, AppSKeyGet as AppSKeyGetVal,
+    DevAddrGet as DevAddrGetVal, DevEuiGet as DevEuiGetVal, DownlinkFrameCountResponse,
+    DrSetResponse, LoraClassGet as LoraClassGetVal, LoraJoinMode, LoraJoinResponse,
+    LoraMaxTxLength, LoraReceivedBytesResponseRaw, LoraRegionGet as LoraRegionGetVal,
+    LoraSendBytesResponseUnprocessed, NwkSKeyGet as NwkSKeyGetVal, UplinkFrameCountResponse,
+};
+#[cfg(feature = "unverified-commands")]
+use super::responses::{
+    ChannelMaskResponse, ChannelRemoveResponse, ChannelResponse, JoinAcceptDelayResponse,
+    Rx1DelayResponse, Rx2FrequencyResponse, TxPowerResponse,
 };
 
-use super::types::{LoraClass, LoraRegion};
+#[cfg(feature = "unverified-commands")]
+use super::types::Channel;
+use super::types::{LoraClass, LoraRegion, LORA_MAX_PAYLOAD};
 
+use crate::error::{Error, ParseError};
 use crate::general::responses::OnOff;
 
 /// 4.3.1 Get Lora Join Mode
@@ -142,6 +150,102 @@ impl AppKeySet {
     }
 }
 
+/// 4.3.5 Get DevAddr
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+DEVADDR=?", DevAddrGetVal)]
+pub struct DevAddrGet {}
+
+/// 4.3.5 Set DevAddr
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+DEVADDR",
+    DevAddrGetVal,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct DevAddrSet {
+    pub dev_addr: HexStr<u32>,
+}
+
+impl DevAddrSet {
+    pub fn dev_addr(val: u32) -> Self {
+        Self {
+            dev_addr: HexStr {
+                val,
+                add_0x_with_encoding: false,
+                hex_in_caps: true,
+                delimiter_after_nibble_count: 2,
+                delimiter: ':',
+                skip_last_0_values: false,
+            },
+        }
+    }
+}
+
+/// 4.3.6 Get AppSKey
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+APPSKEY=?", AppSKeyGetVal)]
+pub struct AppSKeyGet {}
+
+/// 4.3.6 Set AppSKey
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+APPSKEY",
+    AppSKeyGetVal,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct AppSKeySet {
+    pub app_skey: HexStr<u128>,
+}
+
+impl AppSKeySet {
+    pub fn app_skey(val: u128) -> Self {
+        Self {
+            app_skey: HexStr {
+                val,
+                add_0x_with_encoding: false,
+                hex_in_caps: true,
+                delimiter_after_nibble_count: 2,
+                delimiter: ':',
+                skip_last_0_values: false,
+            },
+        }
+    }
+}
+
+/// 4.3.7 Get NwkSKey
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+NWKSKEY=?", NwkSKeyGetVal)]
+pub struct NwkSKeyGet {}
+
+/// 4.3.7 Set NwkSKey
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+NWKSKEY",
+    NwkSKeyGetVal,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct NwkSKeySet {
+    pub nwk_skey: HexStr<u128>,
+}
+
+impl NwkSKeySet {
+    pub fn nwk_skey(val: u128) -> Self {
+        Self {
+            nwk_skey: HexStr {
+                val,
+                add_0x_with_encoding: false,
+                hex_in_caps: true,
+                delimiter_after_nibble_count: 2,
+                delimiter: ':',
+                skip_last_0_values: false,
+            },
+        }
+    }
+}
+
 /// 4.3.8 Region Get
 #[derive(Clone, Debug, AtatCmd)]
 #[at_cmd("+REGION=?", LoraRegionGetVal)]
@@ -223,6 +327,11 @@ impl LoraAutoJoinSet {
     }
 }
 
+/// 4.3.15 ADR get
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+ADR=?", OnOff, timeout_ms = 4000)]
+pub struct LoraAdrGet {}
+
 /// 4.3.15 ADR set
 #[derive(Clone, Debug, AtatCmd)]
 #[at_cmd("+ADR", OnOff, quote_escape_strings = false, timeout_ms = 4000)]
@@ -243,6 +352,31 @@ impl LoraAdrSet {
     }
 }
 
+/// TX power index get. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+TX_POWER=?", TxPowerResponse, timeout_ms = 4000)]
+pub struct TxPowerGet {}
+
+/// TX power index set. 0 is the max EIRP of the region, see
+/// [TxPower](super::types::TxPower). Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+TX_POWER",
+    TxPowerResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct TxPowerSet {
+    pub tx_power: u8,
+}
+
+/// 4.3.17 Data rate (DR) get
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+DR=?", DrSetResponse, timeout_ms = 4000)]
+pub struct LoraDrGet {}
+
 /// 4.3.17 Data rate (DR) set. 0 - 15
 #[derive(Clone, Debug, AtatCmd)]
 #[at_cmd("+DR", DrSetResponse, quote_escape_strings = false, timeout_ms = 4000)]
@@ -250,6 +384,201 @@ pub struct LoraDrSet {
     pub data_rate: u8,
 }
 
+/// RX1 delay get, in seconds after the uplink. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+RX1_DELAY=?", Rx1DelayResponse, timeout_ms = 4000)]
+pub struct Rx1DelayGet {}
+
+/// RX1 delay set. 1 - 15 s, RX2 opens a second later. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+RX1_DELAY",
+    Rx1DelayResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct Rx1DelaySet {
+    pub delay: u8,
+}
+
+/// RX2 frequency get, in Hz. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+RX2_FREQ=?", Rx2FrequencyResponse, timeout_ms = 4000)]
+pub struct Rx2FrequencyGet {}
+
+/// RX2 frequency set, in Hz. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+RX2_FREQ",
+    Rx2FrequencyResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct Rx2FrequencySet {
+    pub frequency: u32,
+}
+
+/// RX2 data rate get. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+RX2_DR=?", DrSetResponse, timeout_ms = 4000)]
+pub struct Rx2DrGet {}
+
+/// RX2 data rate set. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+RX2_DR",
+    DrSetResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct Rx2DrSet {
+    pub data_rate: u8,
+}
+
+/// Join accept delay 1 get, in seconds after the join request. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+JOIN_DELAY1=?", JoinAcceptDelayResponse, timeout_ms = 4000)]
+pub struct JoinAcceptDelay1Get {}
+
+/// Join accept delay 1 set. 1 - 15 s. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+JOIN_DELAY1",
+    JoinAcceptDelayResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct JoinAcceptDelay1Set {
+    pub delay: u8,
+}
+
+/// Join accept delay 2 get, in seconds after the join request. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+JOIN_DELAY2=?", JoinAcceptDelayResponse, timeout_ms = 4000)]
+pub struct JoinAcceptDelay2Get {}
+
+/// Join accept delay 2 set. 1 - 15 s. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+JOIN_DELAY2",
+    JoinAcceptDelayResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct JoinAcceptDelay2Set {
+    pub delay: u8,
+}
+
+/// Channel mask get, US915 and AU915 only. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd("+CH_MASK=?", ChannelMaskResponse, timeout_ms = 4000)]
+pub struct ChannelMaskGet {}
+
+/// Channel mask set, US915 and AU915 only. Formatted by
+/// [ChannelMask::set_cmd](super::types::ChannelMask::set_cmd). Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+CH_MASK",
+    ChannelMaskResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct ChannelMaskSet {
+    pub mask: String<24>,
+}
+
+/// Channel add, EU868, IN865, RU864 and AS923 only. Fields are separated by : so the
+/// command is formatted by [ChannelAdd::new]. Adding a channel that exists replaces it.
+/// Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+CH_ADD",
+    ChannelResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct ChannelAdd {
+    pub val: String<24>,
+}
+
+#[cfg(feature = "unverified-commands")]
+impl ChannelAdd {
+    /// `index:frequency:min_dr:max_dr`, the channel is enabled
+    pub fn new(index: u8, channel: &Channel) -> Self {
+        let mut val = String::new();
+        // Fits, at most 3 + 10 + 3 + 3 digits and 3 separators
+        write!(
+            val,
+            "{}:{}:{}:{}",
+            index, channel.frequency, channel.min_dr, channel.max_dr
+        )
+        .ok();
+        ChannelAdd { val }
+    }
+}
+
+/// Channel remove. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+CH_RM",
+    ChannelRemoveResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct ChannelRemove {
+    pub index: u8,
+}
+
+/// Channel enable / disable, formatted by [ChannelEnable::new]. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+CH_EN",
+    ChannelResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct ChannelEnable {
+    pub val: String<8>,
+}
+
+#[cfg(feature = "unverified-commands")]
+impl ChannelEnable {
+    /// `index:ON` or `index:OFF`
+    pub fn new(index: u8, enabled: bool) -> Self {
+        let mut val = String::new();
+        write!(val, "{}:{}", index, if enabled { "ON" } else { "OFF" }).ok();
+        ChannelEnable { val }
+    }
+}
+
+/// Channel info. Unverified, see the README.
+#[cfg(feature = "unverified-commands")]
+#[derive(Clone, Debug, AtatCmd)]
+#[at_cmd(
+    "+CH_INFO",
+    ChannelResponse,
+    quote_escape_strings = false,
+    timeout_ms = 4000
+)]
+pub struct ChannelGet {
+    pub index: u8,
+}
+
 /// 4.4.1 Maximum TX length get
 #[derive(Clone, Debug, AtatCmd)]
 #[at_cmd("+TX_LEN=?", LoraMaxTxLength, quote_escape_strings = false)]
@@ -280,20 +609,8 @@ impl UplinkConfirmSet {
     }
 }
 
-/// 4.4.3 Send bytes, unprocessed. The AT command sent is wrong and needs , replaced with :
-#[derive(Clone, Debug, AtatCmd)]
-#[at_cmd(
-    "+SENDB",
-    LoraSendBytesResponseUnprocessed,
-    quote_escape_strings = false
-)]
-pub struct SendBytesUnprocessed {
-    pub retransmission_times: u8,
-    pub port: u8,
-    pub data: HexStr<[u8; 256]>,
-}
-
-/// 4.4.3 Send bytes, processed.
+/// 4.4.3 Send bytes. Fields are separated by : instead of , so the command is formatted by
+/// [SendBytes::new]
 #[derive(Clone, Debug, AtatCmd)]
 #[at_cmd(
     "+SENDB",
@@ -301,56 +618,33 @@ pub struct SendBytesUnprocessed {
     quote_escape_strings = false
 )]
 pub struct SendBytes {
-    pub val: String<288>,
-}
-
-impl SendBytesUnprocessed {
-    pub fn processed(self) -> SendBytes {
-        let mut val: String<288> = serde_at::ser::to_string(
-            &self,
-            "",
-            SerializeOptions {
-                value_sep: false,
-                cmd_prefix: "",
-                termination: "",
-                quote_escape_strings: false,
-            },
-        )
-        .unwrap();
-        unsafe {
-            for b in String::as_mut_vec(&mut val) {
-                if *b == b',' {
-                    *b = b':';
-                }
-            }
-        }
-        SendBytes {
-            val: String::from_str(&val).unwrap(),
-        }
-    }
+    pub val: String<SEND_BYTES_LEN>,
 }
 
+/// `255:255:` followed by the hex encoded payload.
+///
+/// Not generic like [LoraReceivedBytes](crate::lora::responses::LoraReceivedBytes): `AtatCmd`
+/// needs the length of the command as a constant, and `8 + 2 * N` can't be derived from a const
+/// generic on stable. The receive side is only generic in the decoded payload, its wire buffer is
+/// fixed as well.
+const SEND_BYTES_LEN: usize = 8 + 2 * LORA_MAX_PAYLOAD;
+
 impl SendBytes {
-    pub fn new(retransmission_times: u8, port: u8, data: &[u8]) -> Self {
-        let mut val = [0u8; 256];
-        for (place, array) in val.iter_mut().zip(data.iter()) {
-            *place = *array;
+    /// Every byte in `data` is sent, including trailing zeroes. Payloads over
+    /// [LORA_MAX_PAYLOAD] are refused with [Error::PayloadTooLarge].
+    pub fn new(retransmission_times: u8, port: u8, data: &[u8]) -> Result<Self, Error> {
+        if data.len() > LORA_MAX_PAYLOAD {
+            return Err(Error::PayloadTooLarge {
+                max: LORA_MAX_PAYLOAD as u16,
+                actual: data.len(),
+            });
         }
-
-        let data = HexStr {
-            val,
-            add_0x_with_encoding: false,
-            hex_in_caps: true,
-            delimiter_after_nibble_count: 0,
-            delimiter: ' ',
-            skip_last_0_values: true,
-        };
-        SendBytesUnprocessed {
-            retransmission_times,
-            port,
-            data,
+        let mut val = String::new();
+        write!(val, "{}:{}:", retransmission_times, port).map_err(|_| ParseError::Overflow)?;
+        for b in data {
+            write!(val, "{:02X}", b).map_err(|_| ParseError::Overflow)?;
         }
-        .processed()
+        Ok(SendBytes { val })
     }
 }
 
@@ -371,13 +665,15 @@ pub struct DownlinkFrameCountGet {}
 
 #[cfg(test)]
 mod tests {
+    use crate::error::Error;
     use crate::lora::commands::{
-        AppEuiGet, AppEuiSet, AppKeyGet, AppKeySet, DevEuiGet, DevEuiSet, JoinModeGet, JoinModeSet,
-        LoraAutoJoinGet, LoraAutoJoinSet, LoraClassGet, LoraJoinOtaa, LoraJoinOtaaStatus,
-        LoraMaxTxLengthGet, LoraReceiveBytes, LoraRegionGet, SendBytes, SendBytesUnprocessed,
+        AppEuiGet, AppEuiSet, AppKeyGet, AppKeySet, AppSKeyGet, AppSKeySet, DevAddrGet, DevAddrSet,
+        DevEuiGet, DevEuiSet, JoinModeGet, JoinModeSet, LoraAdrGet, LoraAdrSet, LoraAutoJoinGet,
+        LoraAutoJoinSet, LoraClassGet, LoraDrGet, LoraDrSet, LoraJoinOtaa, LoraJoinOtaaStatus,
+        LoraMaxTxLengthGet, LoraReceiveBytes, LoraRegionGet, NwkSKeyGet, NwkSKeySet, SendBytes,
         UplinkConfirmGet, UplinkConfirmSet,
     };
-    use crate::lora::types::{LoraClass, LoraRegion};
+    use crate::lora::types::{LoraClass, LoraRegion, LORA_MAX_PAYLOAD};
     use atat::AtatCmd;
 
     #[test]
@@ -433,6 +729,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dev_addr_get() {
+        let k = DevAddrGet {}.as_bytes();
+        assert_eq!(k, b"AT+DEVADDR=?\r\n");
+    }
+
+    #[test]
+    fn dev_addr_set() {
+        let k = DevAddrSet::dev_addr(0x260B1F2A).as_bytes();
+        assert_eq!(k, b"AT+DEVADDR=26:0B:1F:2A\r\n");
+    }
+
+    #[test]
+    fn app_skey_get() {
+        let k = AppSKeyGet {}.as_bytes();
+        assert_eq!(k, b"AT+APPSKEY=?\r\n");
+    }
+
+    #[test]
+    fn app_skey_set() {
+        let k = AppSKeySet::app_skey(0x303235386B375F03_303235386B375F03).as_bytes();
+        assert_eq!(
+            k,
+            b"AT+APPSKEY=30:32:35:38:6B:37:5F:03:30:32:35:38:6B:37:5F:03\r\n"
+        );
+    }
+
+    #[test]
+    fn nwk_skey_get() {
+        let k = NwkSKeyGet {}.as_bytes();
+        assert_eq!(k, b"AT+NWKSKEY=?\r\n");
+    }
+
+    #[test]
+    fn nwk_skey_set() {
+        let k = NwkSKeySet::nwk_skey(0x303235386B375F03_303235386B375F03).as_bytes();
+        assert_eq!(
+            k,
+            b"AT+NWKSKEY=30:32:35:38:6B:37:5F:03:30:32:35:38:6B:37:5F:03\r\n"
+        );
+    }
+
     #[test]
     fn lora_region_get() {
         let k = LoraRegionGet {}.as_bytes();
@@ -483,6 +821,119 @@ mod tests {
         assert_eq!(k, b"AT+AUTO_JOIN=OFF\r\n");
     }
 
+    #[test]
+    fn lora_adr_get() {
+        let k = LoraAdrGet {}.as_bytes();
+        assert_eq!(k, b"AT+ADR=?\r\n");
+    }
+
+    #[test]
+    fn lora_adr_set() {
+        let k = LoraAdrSet::on().as_bytes();
+        assert_eq!(k, b"AT+ADR=ON\r\n");
+        let k = LoraAdrSet::off().as_bytes();
+        assert_eq!(k, b"AT+ADR=OFF\r\n");
+    }
+
+    #[test]
+    fn lora_dr_get() {
+        let k = LoraDrGet {}.as_bytes();
+        assert_eq!(k, b"AT+DR=?\r\n");
+    }
+
+    #[test]
+    fn lora_dr_set() {
+        let k = LoraDrSet { data_rate: 5 }.as_bytes();
+        assert_eq!(k, b"AT+DR=5\r\n");
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn tx_power() {
+        use crate::lora::commands::TxPowerGet;
+        use crate::lora::types::TxPower;
+
+        let k = TxPowerGet {}.as_bytes();
+        assert_eq!(k, b"AT+TX_POWER=?\r\n");
+        let k = TxPower::from_index(3).set_cmd().as_bytes();
+        assert_eq!(k, b"AT+TX_POWER=3\r\n");
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn rx_windows() {
+        use crate::lora::commands::{
+            Rx1DelayGet, Rx1DelaySet, Rx2DrGet, Rx2DrSet, Rx2FrequencyGet, Rx2FrequencySet,
+        };
+
+        let k = Rx1DelayGet {}.as_bytes();
+        assert_eq!(k, b"AT+RX1_DELAY=?\r\n");
+        let k = Rx1DelaySet { delay: 2 }.as_bytes();
+        assert_eq!(k, b"AT+RX1_DELAY=2\r\n");
+        let k = Rx2FrequencyGet {}.as_bytes();
+        assert_eq!(k, b"AT+RX2_FREQ=?\r\n");
+        let k = Rx2FrequencySet {
+            frequency: 869_525_000,
+        }
+        .as_bytes();
+        assert_eq!(k, b"AT+RX2_FREQ=869525000\r\n");
+        let k = Rx2DrGet {}.as_bytes();
+        assert_eq!(k, b"AT+RX2_DR=?\r\n");
+        let k = Rx2DrSet { data_rate: 3 }.as_bytes();
+        assert_eq!(k, b"AT+RX2_DR=3\r\n");
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn join_accept_delays() {
+        use crate::lora::commands::{
+            JoinAcceptDelay1Get, JoinAcceptDelay1Set, JoinAcceptDelay2Get, JoinAcceptDelay2Set,
+        };
+
+        let k = JoinAcceptDelay1Get {}.as_bytes();
+        assert_eq!(k, b"AT+JOIN_DELAY1=?\r\n");
+        let k = JoinAcceptDelay1Set { delay: 5 }.as_bytes();
+        assert_eq!(k, b"AT+JOIN_DELAY1=5\r\n");
+        let k = JoinAcceptDelay2Get {}.as_bytes();
+        assert_eq!(k, b"AT+JOIN_DELAY2=?\r\n");
+        let k = JoinAcceptDelay2Set { delay: 6 }.as_bytes();
+        assert_eq!(k, b"AT+JOIN_DELAY2=6\r\n");
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn channel_mask() {
+        use crate::lora::commands::ChannelMaskGet;
+        use crate::lora::types::ChannelMask;
+
+        let k = ChannelMaskGet {}.as_bytes();
+        assert_eq!(k, b"AT+CH_MASK=?\r\n");
+        let k = ChannelMask::sub_band(2).unwrap().set_cmd().as_bytes();
+        assert_eq!(k, b"AT+CH_MASK=FF00:0000:0000:0000:0002\r\n");
+        let k = ChannelMask::all().set_cmd().as_bytes();
+        assert_eq!(k, b"AT+CH_MASK=FFFF:FFFF:FFFF:FFFF:00FF\r\n");
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn channels() {
+        use crate::lora::commands::{ChannelAdd, ChannelEnable, ChannelGet, ChannelRemove};
+        use crate::lora::types::Channel;
+
+        let k = ChannelAdd::new(3, &Channel::new(867_100_000, 0, 5)).as_bytes();
+        assert_eq!(k, b"AT+CH_ADD=3:867100000:0:5\r\n");
+        let k = ChannelAdd::new(15, &Channel::new(4_294_967_295, 255, 255));
+        assert_eq!(k.val.as_str(), "15:4294967295:255:255");
+        let k = ChannelRemove { index: 3 }.as_bytes();
+        assert_eq!(k, b"AT+CH_RM=3\r\n");
+        let k = ChannelEnable::new(3, false).as_bytes();
+        assert_eq!(k, b"AT+CH_EN=3:OFF\r\n");
+        let k = ChannelEnable::new(15, true).as_bytes();
+        assert_eq!(k, b"AT+CH_EN=15:ON\r\n");
+        let k = ChannelGet { index: 3 }.as_bytes();
+        assert_eq!(k, b"AT+CH_INFO=3\r\n");
+    }
+
     #[test]
     fn max_tx_len_get() {
         let k = LoraMaxTxLengthGet {}.as_bytes();
@@ -505,15 +956,34 @@ mod tests {
 
     #[test]
     fn send_bytes() {
-        let mut v = [0; 256];
-        v[0] = 0xAB;
-        v[1] = 0xCD;
-        v[2] = 0xEF;
-        v[3] = 0x01;
-        let k = SendBytes::new(3, 12, &v).as_bytes();
+        let k = SendBytes::new(3, 12, &[0xAB, 0xCD, 0xEF, 0x01])
+            .unwrap()
+            .as_bytes();
         assert_eq!(k, b"AT+SENDB=3:12:ABCDEF01\r\n");
     }
 
+    #[test]
+    fn send_bytes_trailing_zeroes() {
+        let k = SendBytes::new(0, 1, &[0x01, 0x00, 0x00])
+            .unwrap()
+            .as_bytes();
+        assert_eq!(k, b"AT+SENDB=0:1:010000\r\n");
+    }
+
+    #[test]
+    fn send_bytes_too_long() {
+        let v = [0xFF; LORA_MAX_PAYLOAD + 1];
+        assert_eq!(
+            SendBytes::new(255, 255, &v).map(|_| ()),
+            Err(Error::PayloadTooLarge {
+                max: LORA_MAX_PAYLOAD as u16,
+                actual: LORA_MAX_PAYLOAD + 1
+            })
+        );
+        let k = SendBytes::new(255, 255, &v[..LORA_MAX_PAYLOAD]).unwrap();
+        assert_eq!(k.val.len(), k.val.capacity());
+    }
+
     #[test]
     fn receive_bytes() {
         let k = LoraReceiveBytes {}.as_bytes();
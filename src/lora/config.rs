@@ -0,0 +1,333 @@
+use crate::error::Error;
+#[cfg(feature = "unverified-commands")]
+use crate::lora::types::{ChannelMask, ChannelPlan, TxPower};
+use crate::lora::types::{LoraClass, LoraJoinMode, LoraRegion};
+
+/// Desired LoRa settings of the module. Settings left at `None` are not touched.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoraConfig {
+    pub region: Option<LoraRegion>,
+    /// US915 and AU915 only
+    #[cfg(feature = "unverified-commands")]
+    pub channel_mask: Option<ChannelMask>,
+    /// EU868, IN865, RU864 and AS923 only
+    #[cfg(feature = "unverified-commands")]
+    pub channel_plan: Option<ChannelPlan>,
+    pub join_mode: Option<LoraJoinMode>,
+    pub dev_eui: Option<u64>,
+    pub app_eui: Option<u64>,
+    pub app_key: Option<u128>,
+    pub dev_addr: Option<u32>,
+    pub nwk_skey: Option<u128>,
+    pub app_skey: Option<u128>,
+    pub class: Option<LoraClass>,
+    pub adr: Option<bool>,
+    pub data_rate: Option<u8>,
+    #[cfg(feature = "unverified-commands")]
+    pub tx_power: Option<TxPower>,
+    /// Seconds
+    #[cfg(feature = "unverified-commands")]
+    pub rx1_delay: Option<u8>,
+    /// Hz
+    #[cfg(feature = "unverified-commands")]
+    pub rx2_frequency: Option<u32>,
+    #[cfg(feature = "unverified-commands")]
+    pub rx2_data_rate: Option<u8>,
+    /// Seconds
+    #[cfg(feature = "unverified-commands")]
+    pub join_accept_delay1: Option<u8>,
+    /// Seconds
+    #[cfg(feature = "unverified-commands")]
+    pub join_accept_delay2: Option<u8>,
+    pub confirm: Option<bool>,
+    pub auto_join: Option<bool>,
+}
+
+impl LoraConfig {
+    /// Settings of `self` that are not already in `actual`, e.g. a desired profile against
+    /// the result of `read_config`
+    pub fn diff(&self, actual: &LoraConfig) -> LoraConfig {
+        fn pick<T: PartialEq + Clone>(desired: &Option<T>, actual: &Option<T>) -> Option<T> {
+            match desired {
+                Some(d) if Some(d) != actual.as_ref() => Some(d.clone()),
+                _ => None,
+            }
+        }
+        LoraConfig {
+            region: pick(&self.region, &actual.region),
+            #[cfg(feature = "unverified-commands")]
+            channel_mask: pick(&self.channel_mask, &actual.channel_mask),
+            #[cfg(feature = "unverified-commands")]
+            channel_plan: pick(&self.channel_plan, &actual.channel_plan),
+            join_mode: pick(&self.join_mode, &actual.join_mode),
+            dev_eui: pick(&self.dev_eui, &actual.dev_eui),
+            app_eui: pick(&self.app_eui, &actual.app_eui),
+            app_key: pick(&self.app_key, &actual.app_key),
+            dev_addr: pick(&self.dev_addr, &actual.dev_addr),
+            nwk_skey: pick(&self.nwk_skey, &actual.nwk_skey),
+            app_skey: pick(&self.app_skey, &actual.app_skey),
+            class: pick(&self.class, &actual.class),
+            adr: pick(&self.adr, &actual.adr),
+            data_rate: pick(&self.data_rate, &actual.data_rate),
+            #[cfg(feature = "unverified-commands")]
+            tx_power: pick(&self.tx_power, &actual.tx_power),
+            #[cfg(feature = "unverified-commands")]
+            rx1_delay: pick(&self.rx1_delay, &actual.rx1_delay),
+            #[cfg(feature = "unverified-commands")]
+            rx2_frequency: pick(&self.rx2_frequency, &actual.rx2_frequency),
+            #[cfg(feature = "unverified-commands")]
+            rx2_data_rate: pick(&self.rx2_data_rate, &actual.rx2_data_rate),
+            #[cfg(feature = "unverified-commands")]
+            join_accept_delay1: pick(&self.join_accept_delay1, &actual.join_accept_delay1),
+            #[cfg(feature = "unverified-commands")]
+            join_accept_delay2: pick(&self.join_accept_delay2, &actual.join_accept_delay2),
+            confirm: pick(&self.confirm, &actual.confirm),
+            auto_join: pick(&self.auto_join, &actual.auto_join),
+        }
+    }
+
+    /// Take over every setting that is set in `other`
+    pub fn merge(&mut self, other: &LoraConfig) {
+        fn take<T: Clone>(current: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                *current = other.clone();
+            }
+        }
+        take(&mut self.region, &other.region);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.channel_mask, &other.channel_mask);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.channel_plan, &other.channel_plan);
+        take(&mut self.join_mode, &other.join_mode);
+        take(&mut self.dev_eui, &other.dev_eui);
+        take(&mut self.app_eui, &other.app_eui);
+        take(&mut self.app_key, &other.app_key);
+        take(&mut self.dev_addr, &other.dev_addr);
+        take(&mut self.nwk_skey, &other.nwk_skey);
+        take(&mut self.app_skey, &other.app_skey);
+        take(&mut self.class, &other.class);
+        take(&mut self.adr, &other.adr);
+        take(&mut self.data_rate, &other.data_rate);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.tx_power, &other.tx_power);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.rx1_delay, &other.rx1_delay);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.rx2_frequency, &other.rx2_frequency);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.rx2_data_rate, &other.rx2_data_rate);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.join_accept_delay1, &other.join_accept_delay1);
+        #[cfg(feature = "unverified-commands")]
+        take(&mut self.join_accept_delay2, &other.join_accept_delay2);
+        take(&mut self.confirm, &other.confirm);
+        take(&mut self.auto_join, &other.auto_join);
+    }
+}
+
+/// What happened to a single setting while applying a [LoraConfig]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldResult {
+    /// Not set in the config
+    Skipped,
+    /// The module already had the desired value
+    Unchanged,
+    /// The desired value was written
+    Written,
+    /// Writing the value failed
+    Failed(Error),
+}
+
+impl FieldResult {
+    pub fn is_failed(&self) -> bool {
+        matches!(self, FieldResult::Failed(_))
+    }
+}
+
+impl<T> From<Result<T, Error>> for FieldResult {
+    fn from(value: Result<T, Error>) -> Self {
+        match value {
+            Ok(_) => FieldResult::Written,
+            Err(e) => FieldResult::Failed(e),
+        }
+    }
+}
+
+/// Per setting outcome of applying a [LoraConfig]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigReport {
+    pub region: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub channel_mask: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub channel_plan: FieldResult,
+    pub join_mode: FieldResult,
+    pub dev_eui: FieldResult,
+    pub app_eui: FieldResult,
+    pub app_key: FieldResult,
+    pub dev_addr: FieldResult,
+    pub nwk_skey: FieldResult,
+    pub app_skey: FieldResult,
+    pub class: FieldResult,
+    pub adr: FieldResult,
+    pub data_rate: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub tx_power: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub rx1_delay: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub rx2_frequency: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub rx2_data_rate: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub join_accept_delay1: FieldResult,
+    #[cfg(feature = "unverified-commands")]
+    pub join_accept_delay2: FieldResult,
+    pub confirm: FieldResult,
+    pub auto_join: FieldResult,
+}
+
+impl Default for ConfigReport {
+    fn default() -> Self {
+        Self {
+            region: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            channel_mask: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            channel_plan: FieldResult::Skipped,
+            join_mode: FieldResult::Skipped,
+            dev_eui: FieldResult::Skipped,
+            app_eui: FieldResult::Skipped,
+            app_key: FieldResult::Skipped,
+            dev_addr: FieldResult::Skipped,
+            nwk_skey: FieldResult::Skipped,
+            app_skey: FieldResult::Skipped,
+            class: FieldResult::Skipped,
+            adr: FieldResult::Skipped,
+            data_rate: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            tx_power: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            rx1_delay: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            rx2_frequency: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            rx2_data_rate: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            join_accept_delay1: FieldResult::Skipped,
+            #[cfg(feature = "unverified-commands")]
+            join_accept_delay2: FieldResult::Skipped,
+            confirm: FieldResult::Skipped,
+            auto_join: FieldResult::Skipped,
+        }
+    }
+}
+
+impl ConfigReport {
+    fn fields(&self) -> impl Iterator<Item = &FieldResult> {
+        [
+            &self.region,
+            #[cfg(feature = "unverified-commands")]
+            &self.channel_mask,
+            #[cfg(feature = "unverified-commands")]
+            &self.channel_plan,
+            &self.join_mode,
+            &self.dev_eui,
+            &self.app_eui,
+            &self.app_key,
+            &self.dev_addr,
+            &self.nwk_skey,
+            &self.app_skey,
+            &self.class,
+            &self.adr,
+            &self.data_rate,
+            #[cfg(feature = "unverified-commands")]
+            &self.tx_power,
+            #[cfg(feature = "unverified-commands")]
+            &self.rx1_delay,
+            #[cfg(feature = "unverified-commands")]
+            &self.rx2_frequency,
+            #[cfg(feature = "unverified-commands")]
+            &self.rx2_data_rate,
+            #[cfg(feature = "unverified-commands")]
+            &self.join_accept_delay1,
+            #[cfg(feature = "unverified-commands")]
+            &self.join_accept_delay2,
+            &self.confirm,
+            &self.auto_join,
+        ]
+        .into_iter()
+    }
+
+    /// No setting failed
+    pub fn is_ok(&self) -> bool {
+        self.first_error().is_none()
+    }
+
+    pub fn first_error(&self) -> Option<&Error> {
+        self.fields().find_map(|f| match f {
+            FieldResult::Failed(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// Number of settings that were written to the module
+    pub fn written(&self) -> usize {
+        self.fields()
+            .filter(|f| **f == FieldResult::Written)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, ModuleError};
+    use crate::lora::config::{ConfigReport, FieldResult, LoraConfig};
+    use crate::lora::types::{LoraClass, LoraRegion};
+
+    #[test]
+    fn config_diff() {
+        let desired = LoraConfig {
+            region: Some(LoraRegion::Eu868),
+            class: Some(LoraClass::ClassC),
+            adr: Some(false),
+            data_rate: Some(5),
+            ..Default::default()
+        };
+        let actual = LoraConfig {
+            region: Some(LoraRegion::Eu868),
+            class: Some(LoraClass::ClassA),
+            adr: Some(false),
+            data_rate: Some(3),
+            confirm: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            desired.diff(&actual),
+            LoraConfig {
+                class: Some(LoraClass::ClassC),
+                data_rate: Some(5),
+                ..Default::default()
+            }
+        );
+        assert_eq!(desired.diff(&desired), LoraConfig::default());
+    }
+
+    #[test]
+    fn config_report() {
+        let mut report = ConfigReport::default();
+        assert!(report.is_ok());
+        assert_eq!(report.written(), 0);
+
+        report.region = FieldResult::Unchanged;
+        report.dev_eui = Ok::<_, Error>(1u64).into();
+        report.class = Err::<(), _>(Error::Module(ModuleError::Busy)).into();
+        assert!(!report.is_ok());
+        assert!(report.class.is_failed());
+        assert_eq!(report.written(), 1);
+        assert_eq!(
+            report.first_error(),
+            Some(&Error::Module(ModuleError::Busy))
+        );
+    }
+}
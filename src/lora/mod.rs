@@ -1,190 +1,1220 @@
 pub mod commands;
+pub mod config;
+#[cfg(feature = "async")]
+pub mod queue;
 pub mod responses;
+pub(crate) mod tx_limits;
 pub mod types;
 
-#[cfg(feature = "async")]
-pub mod asynch {
-    use crate::client::asynch::MokoMkl62BaClient;
-    use crate::lora::responses::LoraReceivedBytes;
-    use crate::lora::{
-        commands,
-        responses::LoraSendBytesResponse,
-        types::{LoraClass, LoraJoinMode, LoraJoiningStatus, LoraRegion},
-    };
-    use atat::asynch::AtatClient;
-    use atat::Error;
-    use embedded_io::asynch::Write;
-
-    impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> MokoMkl62BaClient<'a, W, INGRESS_BUF_SIZE> {
-        pub async fn join_mode(&mut self) -> Result<LoraJoinMode, Error> {
+/// The LoRa commands of both clients, from one source so they can't drift apart. Expanded
+/// inside the client impls of `asynch` and `blocking`, with `async` and `.await` for the async
+/// client and nothing for the blocking one. Names are resolved where it is expanded.
+#[cfg(any(feature = "async", feature = "blocking"))]
+macro_rules! lora_commands {
+    (async: [$($async:tt)*], await: [$($await:tt)*]) => {
+        pub $($async)* fn join_mode(&mut self) -> Result<LoraJoinMode, Error> {
             let command = commands::JoinModeGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.mode())
         }
 
-        pub async fn join_mode_set(&mut self, mode: LoraJoinMode) -> Result<LoraJoinMode, Error> {
+        pub $($async)* fn join_mode_set(
+            &mut self,
+            mode: LoraJoinMode,
+        ) -> Result<LoraJoinMode, Error> {
             let command = match mode {
                 LoraJoinMode::Otaa => commands::JoinModeSet::otaa(),
                 LoraJoinMode::Abp => commands::JoinModeSet::abp(),
-                _ => return Err(Error::Error),
+                _ => return Err(Error::InvalidArgument),
             };
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.mode())
         }
 
-        pub async fn dev_eui(&mut self) -> Result<u64, Error> {
+        pub $($async)* fn dev_eui(&mut self) -> Result<u64, Error> {
             let command = commands::DevEuiGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.dev_eui.val)
         }
 
-        pub async fn dev_eui_set(&mut self, dev_eui: u64) -> Result<u64, Error> {
+        pub $($async)* fn dev_eui_set(&mut self, dev_eui: u64) -> Result<u64, Error> {
             let command = commands::DevEuiSet::dev_eui(dev_eui);
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.dev_eui.val)
         }
 
-        pub async fn app_eui(&mut self) -> Result<u64, Error> {
+        pub $($async)* fn app_eui(&mut self) -> Result<u64, Error> {
             let command = commands::AppEuiGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.app_eui.val)
         }
 
-        pub async fn app_eui_set(&mut self, app_eui: u64) -> Result<u64, Error> {
+        pub $($async)* fn app_eui_set(&mut self, app_eui: u64) -> Result<u64, Error> {
             let command = commands::AppEuiSet::app_eui(app_eui);
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.app_eui.val)
         }
 
-        pub async fn app_key(&mut self) -> Result<u128, Error> {
+        pub $($async)* fn app_key(&mut self) -> Result<u128, Error> {
             let command = commands::AppKeyGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.app_key.val)
         }
 
-        pub async fn app_key_set(&mut self, app_key: u128) -> Result<u128, Error> {
+        pub $($async)* fn app_key_set(&mut self, app_key: u128) -> Result<u128, Error> {
             let command = commands::AppKeySet::app_key(app_key);
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.app_key.val)
         }
 
-        pub async fn lora_region(&mut self) -> Result<LoraRegion, Error> {
+        pub $($async)* fn dev_addr(&mut self) -> Result<u32, Error> {
+            let command = commands::DevAddrGet {};
+            let response = self.command(&command)$($await)*?;
+            Ok(response.dev_addr.val)
+        }
+
+        pub $($async)* fn dev_addr_set(&mut self, dev_addr: u32) -> Result<u32, Error> {
+            let command = commands::DevAddrSet::dev_addr(dev_addr);
+            let response = self.command(&command)$($await)*?;
+            Ok(response.dev_addr.val)
+        }
+
+        pub $($async)* fn app_skey(&mut self) -> Result<u128, Error> {
+            let command = commands::AppSKeyGet {};
+            let response = self.command(&command)$($await)*?;
+            Ok(response.app_skey.val)
+        }
+
+        pub $($async)* fn app_skey_set(&mut self, app_skey: u128) -> Result<u128, Error> {
+            let command = commands::AppSKeySet::app_skey(app_skey);
+            let response = self.command(&command)$($await)*?;
+            Ok(response.app_skey.val)
+        }
+
+        pub $($async)* fn nwk_skey(&mut self) -> Result<u128, Error> {
+            let command = commands::NwkSKeyGet {};
+            let response = self.command(&command)$($await)*?;
+            Ok(response.nwk_skey.val)
+        }
+
+        pub $($async)* fn nwk_skey_set(&mut self, nwk_skey: u128) -> Result<u128, Error> {
+            let command = commands::NwkSKeySet::nwk_skey(nwk_skey);
+            let response = self.command(&command)$($await)*?;
+            Ok(response.nwk_skey.val)
+        }
+
+        /// Switch the module to ABP and write the session address and keys, so that it can send
+        /// without going through an OTAA join.
+        pub $($async)* fn provision_abp(
+            &mut self,
+            dev_addr: u32,
+            nwk_skey: u128,
+            app_skey: u128,
+        ) -> Result<(), Error> {
+            self.join_mode_set(LoraJoinMode::Abp)$($await)*?;
+            self.dev_addr_set(dev_addr)$($await)*?;
+            self.nwk_skey_set(nwk_skey)$($await)*?;
+            self.app_skey_set(app_skey)$($await)*?;
+            Ok(())
+        }
+
+        pub $($async)* fn lora_region(&mut self) -> Result<LoraRegion, Error> {
             let command = commands::LoraRegionGet {};
-            let response = self.client.send(&command).await?;
-            Ok(response.into())
+            let response = self.command(&command)$($await)*?;
+            let region: LoraRegion = response.into();
+            self.tx_limits.region = Some(region.clone());
+            Ok(region)
         }
 
-        pub async fn lora_region_set(&mut self, region: LoraRegion) -> Result<LoraRegion, Error> {
+        pub $($async)* fn lora_region_set(
+            &mut self,
+            region: LoraRegion,
+        ) -> Result<LoraRegion, Error> {
             let command = commands::LoraRegionSet::region(region);
-            let response = self.client.send(&command).await?;
-            Ok(response.into())
+            let response = self.command(&command)$($await)*?;
+            let region: LoraRegion = response.into();
+            self.tx_limits.region_changed(region.clone());
+            Ok(region)
         }
 
-        pub async fn lora_class(&mut self) -> Result<LoraClass, Error> {
+        pub $($async)* fn lora_class(&mut self) -> Result<LoraClass, Error> {
             let command = commands::LoraClassGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.into())
         }
 
-        pub async fn lora_class_set(&mut self, class: LoraClass) -> Result<LoraClass, Error> {
+        pub $($async)* fn lora_class_set(&mut self, class: LoraClass) -> Result<LoraClass, Error> {
             let command = commands::LoraClassSet::class(class);
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.into())
         }
 
-        pub async fn lora_join_otaa(&mut self) -> Result<LoraJoiningStatus, Error> {
+        pub $($async)* fn lora_join_otaa(&mut self) -> Result<LoraJoiningStatus, Error> {
             let command = commands::LoraJoinOtaa {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.into())
         }
 
-        pub async fn lora_join_status(&mut self) -> Result<LoraJoiningStatus, Error> {
+        pub $($async)* fn lora_join_status(&mut self) -> Result<LoraJoiningStatus, Error> {
             let command = commands::LoraJoinOtaaStatus {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.into())
         }
 
-        pub async fn auto_join(&mut self) -> Result<bool, Error> {
+        pub $($async)* fn auto_join(&mut self) -> Result<bool, Error> {
             let command = commands::LoraAutoJoinGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.is_on())
         }
 
-        pub async fn auto_join_set(&mut self, is_on: bool) -> Result<bool, Error> {
+        pub $($async)* fn auto_join_set(&mut self, is_on: bool) -> Result<bool, Error> {
             let command = if is_on {
                 commands::LoraAutoJoinSet::on()
             } else {
                 commands::LoraAutoJoinSet::off()
             };
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.is_on())
         }
 
-        pub async fn max_tx_len(&mut self) -> Result<u16, Error> {
+        pub $($async)* fn max_tx_len(&mut self) -> Result<u16, Error> {
             let command = commands::LoraMaxTxLengthGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
+            self.tx_limits.max_tx_len = Some(response.max);
             Ok(response.max)
         }
 
-        pub async fn confirm_send(&mut self) -> Result<bool, Error> {
+        pub $($async)* fn confirm_send(&mut self) -> Result<bool, Error> {
             let command = commands::UplinkConfirmGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.is_on())
         }
 
-        pub async fn confirm_send_set(&mut self, is_on: bool) -> Result<bool, Error> {
+        pub $($async)* fn confirm_send_set(&mut self, is_on: bool) -> Result<bool, Error> {
             let command = if is_on {
                 commands::UplinkConfirmSet::on()
             } else {
                 commands::UplinkConfirmSet::off()
             };
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.is_on())
         }
 
-        pub async fn send(
-            &mut self,
-            retransmission_times: u8,
-            port: u8,
-            data: &[u8],
-        ) -> Result<LoraSendBytesResponse, Error> {
-            let command = commands::SendBytes::new(retransmission_times, port, data);
-            let response = self.client.send(&command).await?;
-            Ok(response.into())
-        }
-
-        pub async fn receive(&mut self) -> Result<LoraReceivedBytes, Error> {
-            let command = commands::LoraReceiveBytes {};
-            let response = self.client.send(&command).await?;
-            Ok(response.into())
+        pub $($async)* fn adr(&mut self) -> Result<bool, Error> {
+            let command = commands::LoraAdrGet {};
+            let response = self.command(&command)$($await)*?;
+            self.tx_limits.adr = Some(response.is_on());
+            Ok(response.is_on())
         }
 
-        pub async fn adr_set(&mut self, on: bool) -> Result<bool, Error> {
+        pub $($async)* fn adr_set(&mut self, on: bool) -> Result<bool, Error> {
             let command = if on {
                 commands::LoraAdrSet::on()
             } else {
                 commands::LoraAdrSet::off()
             };
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
+            self.tx_limits.adr_changed(response.is_on());
             Ok(response.is_on())
         }
 
-        pub async fn dr_set(&mut self, data_rate: u8) -> Result<u8, Error> {
+        pub $($async)* fn dr(&mut self) -> Result<u8, Error> {
+            let command = commands::LoraDrGet {};
+            let response = self.command(&command)$($await)*?;
+            if self.tx_limits.data_rate != Some(response.data_rate) {
+                self.tx_limits.data_rate_changed(response.data_rate);
+            }
+            Ok(response.data_rate)
+        }
+
+        pub $($async)* fn dr_set(&mut self, data_rate: u8) -> Result<u8, Error> {
             let command = commands::LoraDrSet { data_rate };
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
+            self.tx_limits.data_rate_changed(response.data_rate);
+            Ok(response.data_rate)
+        }
+
+        /// Region the settings are validated against, read from the module if not known yet
+        $($async)* fn known_region(&mut self) -> Result<LoraRegion, Error> {
+            match &self.tx_limits.region {
+                Some(region) => Ok(region.clone()),
+                None => self.lora_region()$($await)*,
+            }
+        }
+
+        /// Refuse `len` bytes with [Error::PayloadTooLarge] if the module (+TX_LEN) or the
+        /// regional parameters for the current data rate don't allow it, reading whatever is not
+        /// cached yet
+        $($async)* fn check_payload_len(&mut self, len: usize) -> Result<(), Error> {
+            // The regional limit needs both, the data rate goes first as reading it may drop
+            // the cached +TX_LEN. ADR decides whether the cache survives the send.
+            if self.tx_limits.region.is_none() {
+                self.lora_region()$($await)*?;
+            }
+            if self.tx_limits.adr.is_none() {
+                self.adr()$($await)*?;
+            }
+            if self.tx_limits.data_rate.is_none() {
+                self.dr()$($await)*?;
+            }
+            let module_max = match self.tx_limits.max_tx_len {
+                Some(max) => max,
+                None => self.max_tx_len()$($await)*?,
+            };
+            self.tx_limits.check(module_max, len)
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn tx_power(&mut self) -> Result<TxPower, Error> {
+            let command = commands::TxPowerGet {};
+            let response = self.command(&command)$($await)*?;
+            Ok(TxPower::from_index(response.tx_power))
+        }
+
+        /// Set the TX power. Powers that are not defined for the module region are refused with
+        /// [Error::InvalidArgument].
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn tx_power_set(&mut self, tx_power: TxPower) -> Result<TxPower, Error> {
+            if !tx_power.is_valid_for(&self.known_region()$($await)*?) {
+                return Err(Error::InvalidArgument);
+            }
+            let response = self.command(&tx_power.set_cmd())$($await)*?;
+            Ok(TxPower::from_index(response.tx_power))
+        }
+
+        /// Seconds between the end of the uplink and the RX1 window
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn rx1_delay(&mut self) -> Result<u8, Error> {
+            let command = commands::Rx1DelayGet {};
+            let response = self.command(&command)$($await)*?;
+            self.session.rx1_delay = Some(response.delay);
+            Ok(response.delay)
+        }
+
+        /// 1 - 15 s, anything else is refused with [Error::InvalidArgument]
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn rx1_delay_set(&mut self, delay: u8) -> Result<u8, Error> {
+            if !RX_DELAY_RANGE.contains(&delay) {
+                return Err(Error::InvalidArgument);
+            }
+            let command = commands::Rx1DelaySet { delay };
+            let response = self.command(&command)$($await)*?;
+            self.session.rx1_delay = Some(response.delay);
+            Ok(response.delay)
+        }
+
+        /// RX2 frequency in Hz
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn rx2_frequency(&mut self) -> Result<u32, Error> {
+            let command = commands::Rx2FrequencyGet {};
+            let response = self.command(&command)$($await)*?;
+            Ok(response.frequency)
+        }
+
+        /// Frequencies outside of the module region band are refused with
+        /// [Error::InvalidArgument]
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn rx2_frequency_set(&mut self, frequency: u32) -> Result<u32, Error> {
+            match self.known_region()$($await)*?.frequency_range() {
+                Some(range) if range.contains(&frequency) => {}
+                _ => return Err(Error::InvalidArgument),
+            }
+            let command = commands::Rx2FrequencySet { frequency };
+            let response = self.command(&command)$($await)*?;
+            Ok(response.frequency)
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn rx2_dr(&mut self) -> Result<u8, Error> {
+            let command = commands::Rx2DrGet {};
+            let response = self.command(&command)$($await)*?;
             Ok(response.data_rate)
         }
 
-        pub async fn uplink_frame_count(&mut self) -> Result<u32, Error> {
+        /// Data rates that are not defined for downlinks in the module region are refused with
+        /// [Error::InvalidArgument]
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn rx2_dr_set(&mut self, data_rate: u8) -> Result<u8, Error> {
+            if !self.known_region()$($await)*?.is_downlink_data_rate(data_rate) {
+                return Err(Error::InvalidArgument);
+            }
+            let command = commands::Rx2DrSet { data_rate };
+            let response = self.command(&command)$($await)*?;
+            Ok(response.data_rate)
+        }
+
+        /// Seconds between the join request and the first join accept window
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn join_accept_delay1(&mut self) -> Result<u8, Error> {
+            let command = commands::JoinAcceptDelay1Get {};
+            let response = self.command(&command)$($await)*?;
+            Ok(response.delay)
+        }
+
+        /// 1 - 15 s, anything else is refused with [Error::InvalidArgument]
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn join_accept_delay1_set(&mut self, delay: u8) -> Result<u8, Error> {
+            if !RX_DELAY_RANGE.contains(&delay) {
+                return Err(Error::InvalidArgument);
+            }
+            let command = commands::JoinAcceptDelay1Set { delay };
+            let response = self.command(&command)$($await)*?;
+            Ok(response.delay)
+        }
+
+        /// Seconds between the join request and the second join accept window
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn join_accept_delay2(&mut self) -> Result<u8, Error> {
+            let command = commands::JoinAcceptDelay2Get {};
+            let response = self.command(&command)$($await)*?;
+            Ok(response.delay)
+        }
+
+        /// 1 - 15 s, anything else is refused with [Error::InvalidArgument]
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn join_accept_delay2_set(&mut self, delay: u8) -> Result<u8, Error> {
+            if !RX_DELAY_RANGE.contains(&delay) {
+                return Err(Error::InvalidArgument);
+            }
+            let command = commands::JoinAcceptDelay2Set { delay };
+            let response = self.command(&command)$($await)*?;
+            Ok(response.delay)
+        }
+
+        /// Enabled channels, US915 and AU915 only
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel_mask(&mut self) -> Result<ChannelMask, Error> {
+            let command = commands::ChannelMaskGet {};
+            let response = self.command(&command)$($await)*?;
+            Ok(response.mask()?)
+        }
+
+        /// Masks the module region does not take are refused with [Error::InvalidArgument], see
+        /// [ChannelMask::is_valid_for]
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel_mask_set(
+            &mut self,
+            mask: ChannelMask,
+        ) -> Result<ChannelMask, Error> {
+            if !mask.is_valid_for(&self.known_region()$($await)*?) {
+                return Err(Error::InvalidArgument);
+            }
+            let response = self.command(&mask.set_cmd())$($await)*?;
+            Ok(response.mask()?)
+        }
+
+        /// Only use the channels of sub-band 1 - 8, see [ChannelMask::sub_band]
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn sub_band_set(&mut self, sub_band: u8) -> Result<ChannelMask, Error> {
+            let mask = ChannelMask::sub_band(sub_band).ok_or(Error::InvalidArgument)?;
+            self.channel_mask_set(mask)$($await)*
+        }
+
+        /// Channel at `index`, `None` if it is not defined. EU868, IN865, RU864 and AS923 only.
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel(&mut self, index: u8) -> Result<Option<Channel>, Error> {
+            let command = commands::ChannelGet { index };
+            let response = self.command(&command)$($await)*?;
+            Ok(response.channel()?.1)
+        }
+
+        /// Define the channel at `index`, enabled. Channels past
+        /// [ChannelPlan::MAX_CHANNELS] or not valid for the module region are refused with
+        /// [Error::InvalidArgument].
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel_add(
+            &mut self,
+            index: u8,
+            channel: &Channel,
+        ) -> Result<Channel, Error> {
+            if index >= ChannelPlan::MAX_CHANNELS
+                || !channel.is_valid_for(&self.known_region()$($await)*?)
+            {
+                return Err(Error::InvalidArgument);
+            }
+            let command = commands::ChannelAdd::new(index, channel);
+            let response = self.command(&command)$($await)*?;
+            response
+                .channel()?
+                .1
+                .ok_or(Error::Parse(ParseError::InvalidFormat))
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel_remove(&mut self, index: u8) -> Result<(), Error> {
+            let command = commands::ChannelRemove { index };
+            self.command(&command)$($await)*?;
+            Ok(())
+        }
+
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel_enable(
+            &mut self,
+            index: u8,
+            enabled: bool,
+        ) -> Result<Channel, Error> {
+            let command = commands::ChannelEnable::new(index, enabled);
+            let response = self.command(&command)$($await)*?;
+            response
+                .channel()?
+                .1
+                .ok_or(Error::Parse(ParseError::InvalidFormat))
+        }
+
+        /// Every channel slot of the module. EU868, IN865, RU864 and AS923 only.
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel_plan(&mut self) -> Result<ChannelPlan, Error> {
+            let mut plan = ChannelPlan::default();
+            for index in 0..ChannelPlan::MAX_CHANNELS {
+                if let Some(channel) = self.channel(index)$($await)*? {
+                    plan.set(index, channel)?;
+                }
+            }
+            Ok(plan)
+        }
+
+        /// Replace the channel plan of the module. A plan that is not valid for the module region
+        /// is refused with [Error::InvalidArgument] before anything is written. Only the
+        /// channels that differ are written, and if the module refuses one, the channels
+        /// written before it are put back.
+        #[cfg(feature = "unverified-commands")]
+        pub $($async)* fn channel_plan_set(&mut self, plan: &ChannelPlan) -> Result<(), Error> {
+            if !plan.is_valid_for(&self.known_region()$($await)*?) {
+                return Err(Error::InvalidArgument);
+            }
+            let current = self.channel_plan()$($await)*?;
+            for index in 0..ChannelPlan::MAX_CHANNELS {
+                if current.get(index) == plan.get(index) {
+                    continue;
+                }
+                if let Err(e) = self.channel_write(index, plan.get(index))$($await)* {
+                    // The failed channel may be half written, e.g. added but not disabled
+                    for written in 0..=index {
+                        if current.get(written) == plan.get(written) {
+                            continue;
+                        }
+                        if self
+                            .channel_write(written, current.get(written))
+                            $($await)*
+                            .is_err()
+                        {
+                            #[cfg(feature = "debug")]
+                            error!("Could not roll back channel");
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+            Ok(())
+        }
+
+        /// Define, disable or remove the channel at `index` to match `channel`
+        #[cfg(feature = "unverified-commands")]
+        $($async)* fn channel_write(
+            &mut self,
+            index: u8,
+            channel: Option<&Channel>,
+        ) -> Result<(), Error> {
+            match channel {
+                None => self.channel_remove(index)$($await)*,
+                Some(channel) => {
+                    self.channel_add(index, channel)$($await)*?;
+                    if !channel.enabled {
+                        self.channel_enable(index, false)$($await)*?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        pub $($async)* fn uplink_frame_count(&mut self) -> Result<u32, Error> {
             let command = commands::UplinkFrameCountGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.uplink_frame_count)
         }
 
-        pub async fn downlink_frame_count(&mut self) -> Result<u32, Error> {
+        pub $($async)* fn downlink_frame_count(&mut self) -> Result<u32, Error> {
             let command = commands::DownlinkFrameCountGet {};
-            let response = self.client.send(&command).await?;
+            let response = self.command(&command)$($await)*?;
             Ok(response.downlink_frame_count)
         }
+
+        /// [read_config](Self::read_config) without recovery
+        $($async)* fn read_config_inner(&mut self) -> Result<LoraConfig, Error> {
+            Ok(LoraConfig {
+                region: Some(self.lora_region()$($await)*?),
+                join_mode: Some(self.join_mode()$($await)*?),
+                dev_eui: Some(self.dev_eui()$($await)*?),
+                app_eui: Some(self.app_eui()$($await)*?),
+                app_key: Some(self.app_key()$($await)*?),
+                dev_addr: Some(self.dev_addr()$($await)*?),
+                nwk_skey: Some(self.nwk_skey()$($await)*?),
+                app_skey: Some(self.app_skey()$($await)*?),
+                class: Some(self.lora_class()$($await)*?),
+                adr: Some(self.adr()$($await)*?),
+                data_rate: Some(self.dr()$($await)*?),
+                #[cfg(feature = "unverified-commands")]
+                tx_power: self.tx_power()$($await)*.ok(),
+                #[cfg(feature = "unverified-commands")]
+                rx1_delay: self.rx1_delay()$($await)*.ok(),
+                #[cfg(feature = "unverified-commands")]
+                rx2_frequency: self.rx2_frequency()$($await)*.ok(),
+                #[cfg(feature = "unverified-commands")]
+                rx2_data_rate: self.rx2_dr()$($await)*.ok(),
+                #[cfg(feature = "unverified-commands")]
+                join_accept_delay1: self.join_accept_delay1()$($await)*.ok(),
+                #[cfg(feature = "unverified-commands")]
+                join_accept_delay2: self.join_accept_delay2()$($await)*.ok(),
+                #[cfg(feature = "unverified-commands")]
+                channel_mask: match self.known_region()$($await)*?.has_fixed_channels() {
+                    true => self.channel_mask()$($await)*.ok(),
+                    false => None,
+                },
+                #[cfg(feature = "unverified-commands")]
+                channel_plan: match self.known_region()$($await)*?.has_dynamic_channels() {
+                    true => self.channel_plan()$($await)*.ok(),
+                    false => None,
+                },
+                confirm: Some(self.confirm_send()$($await)*?),
+                auto_join: Some(self.auto_join()$($await)*?),
+            })
+        }
+
+        /// [apply_config](Self::apply_config) without recovery, which the async client uses to
+        /// restore the config
+        pub(crate) $($async)* fn apply_config_inner(
+            &mut self,
+            config: &LoraConfig,
+        ) -> ConfigReport {
+            let mut report = ConfigReport::default();
+            if let Some(region) = &config.region {
+                report.region = match self.lora_region()$($await)* {
+                    Ok(current) if current == *region => FieldResult::Unchanged,
+                    _ => self.lora_region_set(region.clone())$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(channel_mask) = config.channel_mask {
+                report.channel_mask = match self.channel_mask()$($await)* {
+                    Ok(current) if current == channel_mask => FieldResult::Unchanged,
+                    _ => self.channel_mask_set(channel_mask)$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(channel_plan) = &config.channel_plan {
+                report.channel_plan = match self.channel_plan()$($await)* {
+                    Ok(current) if current == *channel_plan => FieldResult::Unchanged,
+                    _ => self.channel_plan_set(channel_plan)$($await)*.into(),
+                };
+            }
+            if let Some(join_mode) = &config.join_mode {
+                report.join_mode = match self.join_mode()$($await)* {
+                    Ok(current) if current == *join_mode => FieldResult::Unchanged,
+                    _ => self.join_mode_set(join_mode.clone())$($await)*.into(),
+                };
+            }
+            if let Some(dev_eui) = config.dev_eui {
+                report.dev_eui = match self.dev_eui()$($await)* {
+                    Ok(current) if current == dev_eui => FieldResult::Unchanged,
+                    _ => self.dev_eui_set(dev_eui)$($await)*.into(),
+                };
+            }
+            if let Some(app_eui) = config.app_eui {
+                report.app_eui = match self.app_eui()$($await)* {
+                    Ok(current) if current == app_eui => FieldResult::Unchanged,
+                    _ => self.app_eui_set(app_eui)$($await)*.into(),
+                };
+            }
+            if let Some(app_key) = config.app_key {
+                report.app_key = match self.app_key()$($await)* {
+                    Ok(current) if current == app_key => FieldResult::Unchanged,
+                    _ => self.app_key_set(app_key)$($await)*.into(),
+                };
+            }
+            if let Some(dev_addr) = config.dev_addr {
+                report.dev_addr = match self.dev_addr()$($await)* {
+                    Ok(current) if current == dev_addr => FieldResult::Unchanged,
+                    _ => self.dev_addr_set(dev_addr)$($await)*.into(),
+                };
+            }
+            if let Some(nwk_skey) = config.nwk_skey {
+                report.nwk_skey = match self.nwk_skey()$($await)* {
+                    Ok(current) if current == nwk_skey => FieldResult::Unchanged,
+                    _ => self.nwk_skey_set(nwk_skey)$($await)*.into(),
+                };
+            }
+            if let Some(app_skey) = config.app_skey {
+                report.app_skey = match self.app_skey()$($await)* {
+                    Ok(current) if current == app_skey => FieldResult::Unchanged,
+                    _ => self.app_skey_set(app_skey)$($await)*.into(),
+                };
+            }
+            if let Some(class) = &config.class {
+                report.class = match self.lora_class()$($await)* {
+                    Ok(current) if current == *class => FieldResult::Unchanged,
+                    _ => self.lora_class_set(class.clone())$($await)*.into(),
+                };
+            }
+            if let Some(adr) = config.adr {
+                report.adr = match self.adr()$($await)* {
+                    Ok(current) if current == adr => FieldResult::Unchanged,
+                    _ => self.adr_set(adr)$($await)*.into(),
+                };
+            }
+            if let Some(data_rate) = config.data_rate {
+                report.data_rate = match self.dr()$($await)* {
+                    Ok(current) if current == data_rate => FieldResult::Unchanged,
+                    _ => self.dr_set(data_rate)$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(tx_power) = config.tx_power {
+                report.tx_power = match self.tx_power()$($await)* {
+                    Ok(current) if current == tx_power => FieldResult::Unchanged,
+                    _ => self.tx_power_set(tx_power)$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(rx1_delay) = config.rx1_delay {
+                report.rx1_delay = match self.rx1_delay()$($await)* {
+                    Ok(current) if current == rx1_delay => FieldResult::Unchanged,
+                    _ => self.rx1_delay_set(rx1_delay)$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(rx2_frequency) = config.rx2_frequency {
+                report.rx2_frequency = match self.rx2_frequency()$($await)* {
+                    Ok(current) if current == rx2_frequency => FieldResult::Unchanged,
+                    _ => self.rx2_frequency_set(rx2_frequency)$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(rx2_data_rate) = config.rx2_data_rate {
+                report.rx2_data_rate = match self.rx2_dr()$($await)* {
+                    Ok(current) if current == rx2_data_rate => FieldResult::Unchanged,
+                    _ => self.rx2_dr_set(rx2_data_rate)$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(join_accept_delay1) = config.join_accept_delay1 {
+                report.join_accept_delay1 = match self.join_accept_delay1()$($await)* {
+                    Ok(current) if current == join_accept_delay1 => FieldResult::Unchanged,
+                    _ => self.join_accept_delay1_set(join_accept_delay1)$($await)*.into(),
+                };
+            }
+            #[cfg(feature = "unverified-commands")]
+            if let Some(join_accept_delay2) = config.join_accept_delay2 {
+                report.join_accept_delay2 = match self.join_accept_delay2()$($await)* {
+                    Ok(current) if current == join_accept_delay2 => FieldResult::Unchanged,
+                    _ => self.join_accept_delay2_set(join_accept_delay2)$($await)*.into(),
+                };
+            }
+            if let Some(confirm) = config.confirm {
+                report.confirm = match self.confirm_send()$($await)* {
+                    Ok(current) if current == confirm => FieldResult::Unchanged,
+                    _ => self.confirm_send_set(confirm)$($await)*.into(),
+                };
+            }
+            if let Some(auto_join) = config.auto_join {
+                report.auto_join = match self.auto_join()$($await)* {
+                    Ok(current) if current == auto_join => FieldResult::Unchanged,
+                    _ => self.auto_join_set(auto_join)$($await)*.into(),
+                };
+            }
+            report
+        }
+    };
+}
+
+#[cfg(feature = "async")]
+pub mod asynch {
+    use crate::client::asynch::MokoMkl62BaClient;
+    #[cfg(feature = "unverified-commands")]
+    use crate::error::ParseError;
+    use crate::error::{Error, ModuleError};
+    use crate::lora::responses::LoraReceivedBytes;
+    #[cfg(feature = "unverified-commands")]
+    use crate::lora::types::{Channel, ChannelMask, ChannelPlan, TxPower, RX_DELAY_RANGE};
+    use crate::lora::{
+        commands,
+        config::{ConfigReport, FieldResult, LoraConfig},
+        responses::LoraSendBytesResponse,
+        types::{
+            DutyCyclePolicy, JoinOutcome, JoinPolicy, LoraClass, LoraJoinMode, LoraJoiningStatus,
+            LoraRegion, OtaaCredentials,
+        },
+    };
+    #[cfg(feature = "debug")]
+    use defmt::error;
+    use embassy_time::{Duration, Instant, Timer};
+    use embedded_io::asynch::Write;
+
+    impl<'a, W: Write, const INGRESS_BUF_SIZE: usize> MokoMkl62BaClient<'a, W, INGRESS_BUF_SIZE> {
+        lora_commands!(async: [async], await: [.await]);
+
+        /// Configure the module for OTAA, then keep joining until it succeeds or the policy
+        /// gives up. Between attempts the backoff from the policy is applied, stretched to the
+        /// module reported `NEXT TX after(s)` if a URC source is set.
+        ///
+        /// Recovers from an unexpected reset first, see
+        /// [recover_if_reset](Self::recover_if_reset).
+        pub async fn join_otaa(
+            &mut self,
+            credentials: &OtaaCredentials,
+            policy: &JoinPolicy,
+        ) -> Result<JoinOutcome, Error> {
+            self.recover_if_reset().await?;
+            self.join_otaa_inner(credentials, policy).await
+        }
+
+        /// [join_otaa](Self::join_otaa) without recovery, which uses it to join again
+        pub(crate) async fn join_otaa_inner(
+            &mut self,
+            credentials: &OtaaCredentials,
+            policy: &JoinPolicy,
+        ) -> Result<JoinOutcome, Error> {
+            self.session.otaa = Some((credentials.clone(), policy.clone()));
+            self.join_mode_set(LoraJoinMode::Otaa).await?;
+            self.dev_eui_set(credentials.dev_eui).await?;
+            self.app_eui_set(credentials.app_eui).await?;
+            self.app_key_set(credentials.app_key).await?;
+
+            let mut attempts = 0;
+            loop {
+                attempts += 1;
+                let status = self.join_attempt(policy).await?;
+                let outcome = match status {
+                    LoraJoiningStatus::Joined => {
+                        self.session.joined = true;
+                        // The join accept may have changed it
+                        self.session.rx1_delay = None;
+                        return Ok(JoinOutcome::Joined { attempts });
+                    }
+                    LoraJoiningStatus::InAbpModeError => return Ok(JoinOutcome::InAbpMode),
+                    LoraJoiningStatus::BusyError => JoinOutcome::Busy,
+                    LoraJoiningStatus::Joining => JoinOutcome::TimedOut { attempts },
+                    LoraJoiningStatus::JoinFailed | LoraJoiningStatus::Unknown => {
+                        JoinOutcome::JoinFailed { attempts }
+                    }
+                };
+                if attempts >= policy.max_attempts {
+                    return Ok(outcome);
+                }
+
+                let backoff = Duration::from_millis(policy.backoff_ms(attempts) as u64);
+                self.poll_urcs();
+                Timer::after(backoff.max(self.next_tx_delay())).await;
+            }
+        }
+
+        /// Single +JOINING attempt, polling +JOIN_STD until it reports a result or the attempt
+        /// times out. A timed out attempt is reported as [LoraJoiningStatus::Joining].
+        async fn join_attempt(&mut self, policy: &JoinPolicy) -> Result<LoraJoiningStatus, Error> {
+            match self.lora_join_otaa().await {
+                Ok(LoraJoiningStatus::BusyError) | Err(Error::Module(ModuleError::Busy)) => {
+                    return Ok(LoraJoiningStatus::BusyError)
+                }
+                Ok(LoraJoiningStatus::InAbpModeError) => {
+                    return Ok(LoraJoiningStatus::InAbpModeError)
+                }
+                // ERROR(-2) only means ABP mode if the module says so, otherwise it is passed on
+                Err(Error::Module(ModuleError::AtParameterError)) => {
+                    return match self.join_mode().await {
+                        Ok(LoraJoinMode::Abp) => Ok(LoraJoiningStatus::InAbpModeError),
+                        _ => Err(Error::Module(ModuleError::AtParameterError)),
+                    };
+                }
+                Ok(_) => {}
+                Err(e) => return Err(e),
+            }
+
+            let deadline = Instant::now() + Duration::from_millis(policy.attempt_timeout_ms as u64);
+            loop {
+                Timer::after(Duration::from_millis(policy.poll_interval_ms as u64)).await;
+                self.poll_urcs();
+                match self.lora_join_status().await? {
+                    LoraJoiningStatus::Joining | LoraJoiningStatus::Unknown => {
+                        if Instant::now() >= deadline {
+                            return Ok(LoraJoiningStatus::Joining);
+                        }
+                    }
+                    status => return Ok(status),
+                }
+            }
+        }
+
+        /// Send `data` as an uplink. Payloads larger than what the module (+TX_LEN) or the
+        /// regional parameters for the current data rate allow are refused with
+        /// [Error::PayloadTooLarge].
+        ///
+        /// While the module reported `NEXT TX after(s)` has not passed, the uplink waits or
+        /// fails with [Error::DutyCycleRestricted], see
+        /// [set_duty_cycle_policy](Self::set_duty_cycle_policy).
+        pub async fn send(
+            &mut self,
+            retransmission_times: u8,
+            port: u8,
+            data: &[u8],
+        ) -> Result<LoraSendBytesResponse, Error> {
+            self.send_uplink(None, retransmission_times, port, data)
+                .await
+        }
+
+        /// Like [send](Self::send), as a confirmed or unconfirmed uplink if `confirm` is set.
+        /// +CONFIRM is switched for this uplink only and set back afterwards.
+        pub(crate) async fn send_uplink(
+            &mut self,
+            confirm: Option<bool>,
+            retransmission_times: u8,
+            port: u8,
+            data: &[u8],
+        ) -> Result<LoraSendBytesResponse, Error> {
+            self.recover_if_reset().await?;
+            if let Some(remaining) = self.duty_cycle_remaining() {
+                match self.duty_cycle_policy {
+                    DutyCyclePolicy::Wait => Timer::after(remaining).await,
+                    DutyCyclePolicy::Reject => {
+                        return Err(Error::DutyCycleRestricted {
+                            retry_after: remaining.as_millis() as u32,
+                        })
+                    }
+                }
+            }
+            self.check_payload_len(data.len()).await?;
+            let command = commands::SendBytes::new(retransmission_times, port, data)?;
+            // Auto sleep falls back to the LoRaWAN default if the module does not tell
+            #[cfg(feature = "unverified-commands")]
+            if self.session.auto_sleep && self.session.rx1_delay.is_none() {
+                self.rx1_delay().await.ok();
+            }
+            let mut previous_confirm = None;
+            if let Some(confirm) = confirm {
+                let previous = self.confirm_send().await?;
+                if previous != confirm {
+                    self.confirm_send_set(confirm).await?;
+                    previous_confirm = Some(previous);
+                }
+            }
+            self.poll_urcs();
+            self.session.downlink_received = false;
+            let response = self.command(&command).await;
+            if let Some(previous) = previous_confirm {
+                // The uplink may be out already, so this does not fail the send
+                if self.confirm_send_set(previous).await.is_err() {
+                    #[cfg(feature = "debug")]
+                    error!("Could not restore +CONFIRM");
+                }
+            }
+            let response = response?;
+            self.tx_limits.sent();
+            self.auto_sleep().await;
+            Ok(response.try_into()?)
+        }
+
+        /// Last downlink or ACK, recovering from an unexpected reset first, see
+        /// [recover_if_reset](Self::recover_if_reset).
+        pub async fn receive(&mut self) -> Result<LoraReceivedBytes, Error> {
+            self.receive_with_capacity().await
+        }
+
+        /// Like [receive](Self::receive), with room for N bytes of payload
+        pub async fn receive_with_capacity<const N: usize>(
+            &mut self,
+        ) -> Result<LoraReceivedBytes<N>, Error> {
+            self.recover_if_reset().await?;
+            let command = commands::LoraReceiveBytes {};
+            self.digester.set_receive_pending(true);
+            let response = self.command(&command).await;
+            self.digester.set_receive_pending(false);
+            Ok(response?.try_into()?)
+        }
+
+        /// Read back every LoRa setting of the module, recovering from an unexpected reset first,
+        /// see [recover_if_reset](Self::recover_if_reset). Settings read with unverified commands
+        /// (see the README) are left at `None` if the module refuses them.
+        pub async fn read_config(&mut self) -> Result<LoraConfig, Error> {
+            self.recover_if_reset().await?;
+            self.read_config_inner().await
+        }
+
+        /// Bring the module in line with `config`. Every setting is read back first and only
+        /// written when it differs, so applying the same config again does not wear the module
+        /// flash.
+        ///
+        /// The region goes first, as changing it resets the region specific settings on the
+        /// module. Recovers from an unexpected reset first, see
+        /// [recover_if_reset](Self::recover_if_reset).
+        pub async fn apply_config(&mut self, config: &LoraConfig) -> ConfigReport {
+            // Failures are logged, `config` is written below either way
+            self.recover_if_reset().await.ok();
+            self.session.config_applied(config);
+            self.apply_config_inner(config).await
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+pub mod blocking {
+    use crate::client::blocking::MokoMkl62BaBlockingClient;
+    use crate::error::Error;
+    #[cfg(feature = "unverified-commands")]
+    use crate::error::ParseError;
+    use crate::lora::responses::LoraReceivedBytes;
+    #[cfg(feature = "unverified-commands")]
+    use crate::lora::types::{Channel, ChannelMask, ChannelPlan, TxPower, RX_DELAY_RANGE};
+    use crate::lora::{
+        commands,
+        config::{ConfigReport, FieldResult, LoraConfig},
+        responses::LoraSendBytesResponse,
+        types::{LoraClass, LoraJoinMode, LoraJoiningStatus, LoraRegion},
+    };
+    #[cfg(all(feature = "debug", feature = "unverified-commands"))]
+    use defmt::error;
+    use embedded_io::blocking::Write;
+
+    impl<'a, W: Write, const INGRESS_BUF_SIZE: usize>
+        MokoMkl62BaBlockingClient<'a, W, INGRESS_BUF_SIZE>
+    {
+        lora_commands!(async: [], await: []);
+
+        /// Send `data` as an uplink. Payloads larger than what the module (+TX_LEN) or the
+        /// regional parameters for the current data rate allow are refused with
+        /// [Error::PayloadTooLarge].
+        pub fn send(
+            &mut self,
+            retransmission_times: u8,
+            port: u8,
+            data: &[u8],
+        ) -> Result<LoraSendBytesResponse, Error> {
+            self.check_payload_len(data.len())?;
+            let command = commands::SendBytes::new(retransmission_times, port, data)?;
+            let response = self.command(&command)?;
+            self.tx_limits.sent();
+            Ok(response.try_into()?)
+        }
+
+        pub fn receive(&mut self) -> Result<LoraReceivedBytes, Error> {
+            self.receive_with_capacity()
+        }
+
+        /// Like [receive](Self::receive), with room for N bytes of payload
+        pub fn receive_with_capacity<const N: usize>(
+            &mut self,
+        ) -> Result<LoraReceivedBytes<N>, Error> {
+            let command = commands::LoraReceiveBytes {};
+            self.digester.set_receive_pending(true);
+            let response = self.command(&command);
+            self.digester.set_receive_pending(false);
+            Ok(response?.try_into()?)
+        }
+
+        /// Read back every LoRa setting of the module. Settings read with unverified commands (see
+        /// the README) are left at `None` if the module refuses them.
+        pub fn read_config(&mut self) -> Result<LoraConfig, Error> {
+            self.read_config_inner()
+        }
+
+        /// Bring the module in line with `config`. Every setting is read back first and only
+        /// written when it differs, so applying the same config again does not wear the module
+        /// flash.
+        ///
+        /// The region goes first, as changing it resets the region specific settings on the
+        /// module.
+        pub fn apply_config(&mut self, config: &LoraConfig) -> ConfigReport {
+            self.apply_config_inner(config)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "async"))]
+mod tests {
+    use crate::error::{Error, ModuleError};
+    use crate::lora::config::LoraConfig;
+    use crate::lora::types::{JoinOutcome, JoinPolicy, LoraClass, OtaaCredentials};
+    use crate::sim::{run_client, Mkl62baSim};
+
+    #[test]
+    fn config() {
+        let sim = Mkl62baSim::new();
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, _| async move {
+                let config = LoraConfig {
+                    class: Some(LoraClass::ClassC),
+                    data_rate: Some(5),
+                    ..Default::default()
+                };
+                assert_eq!(client.apply_config(&config).await.written(), 2);
+                assert_eq!(client.apply_config(&config).await.written(), 0);
+                let read = client.read_config().await.unwrap();
+                assert_eq!(read.class, Some(LoraClass::ClassC));
+                assert_eq!(read.data_rate, Some(5));
+            },
+        );
+        sim.with_state(|s| {
+            assert_eq!(s.class, LoraClass::ClassC);
+            assert_eq!(s.data_rate, 5);
+        });
+    }
+
+    #[test]
+    fn tx_limits_cache() {
+        for adr in [false, true] {
+            let sim = Mkl62baSim::new();
+            sim.with_state(|s| {
+                s.joined = true;
+                s.adr = adr;
+            });
+            run_client(
+                &sim,
+                |builder| builder.skip_reset(),
+                |mut client, _| async move {
+                    client.send(0, 8, b"Hello").await.unwrap();
+                    client.send(0, 8, b"Again").await.unwrap();
+                },
+            );
+            // With ADR on the network may have moved the data rate after each uplink
+            let reads = if adr { 2 } else { 1 };
+            sim.with_state(|s| {
+                let count = |command: &str| s.commands.iter().filter(|c| *c == command).count();
+                assert_eq!(count("+REGION=?"), 1);
+                assert_eq!(count("+ADR=?"), 1);
+                assert_eq!(count("+DR=?"), reads);
+                assert_eq!(count("+TX_LEN=?"), reads);
+                assert_eq!(s.uplinks.len(), 2);
+            });
+        }
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn read_config_best_effort() {
+        let sim = Mkl62baSim::new();
+        sim.fail_command("+RX1_DELAY", -1);
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, _| async move {
+                let read = client.read_config().await.unwrap();
+                assert_eq!(read.rx1_delay, None);
+                assert_eq!(read.rx2_frequency, Some(869_525_000));
+                assert_eq!(read.class, Some(LoraClass::ClassA));
+            },
+        );
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn unverified_config() {
+        use crate::lora::types::TxPower;
+
+        let sim = Mkl62baSim::new();
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, _| async move {
+                let config = LoraConfig {
+                    tx_power: Some(TxPower::from_index(2)),
+                    rx2_frequency: Some(869_525_000),
+                    rx2_data_rate: Some(3),
+                    ..Default::default()
+                };
+                // The RX2 frequency is the EU868 default already
+                assert_eq!(client.apply_config(&config).await.written(), 2);
+                assert_eq!(client.apply_config(&config).await.written(), 0);
+                let read = client.read_config().await.unwrap();
+                assert_eq!(read.tx_power, Some(TxPower::from_index(2)));
+
+                // Not defined in EU868
+                let config = LoraConfig {
+                    tx_power: Some(TxPower::from_index(8)),
+                    ..Default::default()
+                };
+                assert!(!client.apply_config(&config).await.is_ok());
+            },
+        );
+        sim.with_state(|s| {
+            assert_eq!(s.tx_power, 2);
+            assert_eq!(s.rx2_data_rate, 3);
+        });
+    }
+
+    #[test]
+    fn join() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.join_polls = 2);
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, sim| async move {
+                let credentials = OtaaCredentials {
+                    dev_eui: 0x68419fa0f7e74b0d,
+                    app_eui: 0,
+                    app_key: 0xd65b042878144e038a744359c7cd1f9d,
+                };
+                let policy = JoinPolicy {
+                    poll_interval_ms: 10,
+                    ..Default::default()
+                };
+                let outcome = client.join_otaa(&credentials, &policy).await.unwrap();
+                assert_eq!(outcome, JoinOutcome::Joined { attempts: 1 });
+                assert!(client.is_joined());
+
+                sim.with_state(|s| {
+                    s.joined = false;
+                    s.join_accept = false;
+                });
+                let policy = JoinPolicy {
+                    max_attempts: 2,
+                    initial_backoff_ms: 10,
+                    ..policy
+                };
+                let outcome = client.join_otaa(&credentials, &policy).await.unwrap();
+                assert_eq!(outcome, JoinOutcome::JoinFailed { attempts: 2 });
+
+                // Refused in OTAA mode, so not mistaken for ABP
+                sim.fail_command("+JOINING", -2);
+                assert_eq!(
+                    client.join_otaa(&credentials, &policy).await,
+                    Err(Error::Module(ModuleError::AtParameterError))
+                );
+            },
+        );
+        sim.with_state(|s| assert_eq!(s.dev_eui, 0x68419fa0f7e74b0d));
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn channel_plan() {
+        use crate::lora::types::Channel;
+
+        let sim = Mkl62baSim::new();
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, _| async move {
+                // Refused by the module halfway, channel 3 is removed again
+                let mut plan = client.channel_plan().await.unwrap();
+                let defaults = plan.clone();
+                plan.set(3, Channel::new(867_100_000, 0, 5)).unwrap();
+                plan.set(4, Channel::new(868_100_000, 0, 5)).unwrap();
+                assert!(client.channel_plan_set(&plan).await.is_err());
+                assert_eq!(client.channel_plan().await.unwrap(), defaults);
+
+                let mut plan = defaults;
+                plan.set(3, Channel::new(867_100_000, 0, 5)).unwrap();
+                plan.set(
+                    4,
+                    Channel {
+                        enabled: false,
+                        ..Channel::new(867_300_000, 0, 5)
+                    },
+                )
+                .unwrap();
+                client.channel_plan_set(&plan).await.unwrap();
+                assert_eq!(client.channel_plan().await.unwrap(), plan);
+            },
+        );
     }
 }
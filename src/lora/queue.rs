@@ -0,0 +1,372 @@
+//! # Uplink queue
+//!
+//! Optional layer on top of [MokoMkl62BaClient] for applications that produce uplinks faster than
+//! the module may send them. Uplinks are kept in bounded storage and sent highest
+//! [priority](UplinkPriority) first, oldest first within a priority. Uplinks the module refuses
+//! with [busy](Error::is_busy) or [Error::DutyCycleRestricted] are retried later, and nothing is
+//! sent while the module reported `NEXT TX after(s)` has not passed. Every uplink can carry a
+//! callback that gets its final result.
+//!
+//! ```ignore
+//! let done = |id, result: &Result<LoraSendBytesResponse, Error>| info!("{} {}", id, result.is_ok());
+//! let mut queue: UplinkQueue<8> = UplinkQueue::new(RetryPolicy::default());
+//! queue.push(Uplink::new(8, b"alarm")?.priority(UplinkPriority::High).confirmed(), Some(&done))?;
+//! queue.flush(&mut client).await;
+//! ```
+
+use crate::client::asynch::MokoMkl62BaClient;
+use crate::error::Error;
+use crate::lora::responses::LoraSendBytesResponse;
+use crate::lora::types::LORA_MAX_PAYLOAD;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_io::asynch::Write;
+use heapless::Vec;
+
+/// Handed out by [UplinkQueue::push], unique until it wraps around
+pub type UplinkId = u32;
+
+/// Called once per uplink with its final result, after the last attempt
+pub type UplinkCallback<'c> = &'c dyn Fn(UplinkId, &Result<LoraSendBytesResponse, Error>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum UplinkPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// An uplink waiting in an [UplinkQueue]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uplink {
+    pub port: u8,
+    pub data: Vec<u8, LORA_MAX_PAYLOAD>,
+    /// Sent as a confirmed uplink. +CONFIRM is switched for this uplink only and set back
+    /// afterwards.
+    pub confirm: bool,
+    pub priority: UplinkPriority,
+    /// Retransmissions done by the module itself, see
+    /// [SendBytes](crate::lora::commands::SendBytes)
+    pub retransmissions: u8,
+}
+
+impl Uplink {
+    /// Unconfirmed uplink at normal priority. Payloads over [LORA_MAX_PAYLOAD] are refused with
+    /// [Error::PayloadTooLarge].
+    pub fn new(port: u8, data: &[u8]) -> Result<Self, Error> {
+        let data = Vec::from_slice(data).map_err(|_| Error::PayloadTooLarge {
+            max: LORA_MAX_PAYLOAD as u16,
+            actual: data.len(),
+        })?;
+        Ok(Self {
+            port,
+            data,
+            confirm: false,
+            priority: UplinkPriority::default(),
+            retransmissions: 0,
+        })
+    }
+
+    pub fn confirmed(mut self) -> Self {
+        self.confirm = true;
+        self
+    }
+
+    pub fn priority(mut self, priority: UplinkPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// How often and when an uplink is retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per uplink, including the first
+    pub max_attempts: u8,
+    /// Wait before retrying an uplink the module refused with ERROR(-3)
+    pub busy_backoff_ms: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            busy_backoff_ms: 1_000,
+        }
+    }
+}
+
+struct Entry<'c> {
+    id: UplinkId,
+    uplink: Uplink,
+    attempts: u8,
+    not_before: Option<Instant>,
+    on_complete: Option<UplinkCallback<'c>>,
+}
+
+/// Holds up to N uplinks, see the [module documentation](self)
+pub struct UplinkQueue<'c, const N: usize> {
+    entries: Vec<Entry<'c>, N>,
+    next_id: UplinkId,
+    policy: RetryPolicy,
+}
+
+impl<'c, const N: usize> UplinkQueue<'c, N> {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            entries: Vec::new(),
+            next_id: 0,
+            policy,
+        }
+    }
+
+    /// Queue an uplink, `on_complete` is called with its final result. The uplink is given
+    /// back if the queue is full.
+    pub fn push(
+        &mut self,
+        uplink: Uplink,
+        on_complete: Option<UplinkCallback<'c>>,
+    ) -> Result<UplinkId, Uplink> {
+        let id = self.next_id;
+        self.entries
+            .push(Entry {
+                id,
+                uplink,
+                attempts: 0,
+                not_before: None,
+                on_complete,
+            })
+            .map_err(|entry| entry.uplink)?;
+        self.next_id = self.next_id.wrapping_add(1);
+        Ok(id)
+    }
+
+    /// Take an uplink out of the queue, its callback is not called
+    pub fn cancel(&mut self, id: UplinkId) -> Option<Uplink> {
+        let index = self.entries.iter().position(|e| e.id == id)?;
+        Some(self.entries.remove(index).uplink)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.is_full()
+    }
+
+    /// Send the next uplink that is due, if the module allows it. Returns how long to wait
+    /// before calling again, `None` once the queue is empty.
+    pub async fn process<W: Write, const INGRESS_BUF_SIZE: usize>(
+        &mut self,
+        client: &mut MokoMkl62BaClient<'_, W, INGRESS_BUF_SIZE>,
+    ) -> Option<Duration> {
+        let index = match self.next_due(Instant::now()) {
+            Ok(index) => index,
+            Err(wait) => return wait,
+        };
+        if let Some(remaining) = client.duty_cycle_remaining() {
+            return Some(remaining);
+        }
+
+        let uplink = &self.entries[index].uplink;
+        let result = client
+            .send_uplink(
+                Some(uplink.confirm),
+                uplink.retransmissions,
+                uplink.port,
+                &uplink.data,
+            )
+            .await;
+        let retry_after = match &result {
+            Err(Error::DutyCycleRestricted { retry_after }) => {
+                Some(Duration::from_millis(*retry_after as u64))
+            }
+            Err(e) if e.is_busy() => {
+                Some(Duration::from_millis(self.policy.busy_backoff_ms as u64))
+            }
+            _ => None,
+        };
+        let entry = &mut self.entries[index];
+        entry.attempts += 1;
+        match retry_after {
+            Some(wait) if entry.attempts < self.policy.max_attempts => {
+                entry.not_before = Some(Instant::now() + wait);
+            }
+            _ => {
+                let entry = self.entries.remove(index);
+                if let Some(on_complete) = entry.on_complete {
+                    on_complete(entry.id, &result);
+                }
+            }
+        }
+
+        match self.next_due(Instant::now()) {
+            Ok(_) => Some(Duration::from_ticks(0)),
+            Err(wait) => wait,
+        }
+    }
+
+    /// Process until every uplink is sent or given up on
+    pub async fn flush<W: Write, const INGRESS_BUF_SIZE: usize>(
+        &mut self,
+        client: &mut MokoMkl62BaClient<'_, W, INGRESS_BUF_SIZE>,
+    ) {
+        while let Some(wait) = self.process(client).await {
+            Timer::after(wait).await;
+        }
+    }
+
+    /// Index of the highest priority uplink that is due, oldest first. Otherwise how long until
+    /// the first one is due, `None` if the queue is empty.
+    fn next_due(&self, now: Instant) -> Result<usize, Option<Duration>> {
+        let mut next: Option<usize> = None;
+        let mut wait: Option<Duration> = None;
+        for (index, entry) in self.entries.iter().enumerate() {
+            match entry.not_before {
+                Some(at) if at > now => {
+                    let until = at - now;
+                    wait = Some(wait.map_or(until, |w| w.min(until)));
+                }
+                _ => {
+                    if next.map_or(true, |n| {
+                        entry.uplink.priority > self.entries[n].uplink.priority
+                    }) {
+                        next = Some(index);
+                    }
+                }
+            }
+        }
+        next.ok_or(wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::lora::queue::{RetryPolicy, Uplink, UplinkPriority, UplinkQueue};
+    use crate::lora::types::LORA_MAX_PAYLOAD;
+    use embassy_time::{Duration, Instant};
+
+    #[test]
+    fn uplink() {
+        let uplink = Uplink::new(8, b"12").unwrap().confirmed();
+        assert!(uplink.confirm);
+        assert_eq!(uplink.priority, UplinkPriority::Normal);
+        assert_eq!(
+            Uplink::new(8, &[0; LORA_MAX_PAYLOAD + 1]),
+            Err(Error::PayloadTooLarge {
+                max: LORA_MAX_PAYLOAD as u16,
+                actual: LORA_MAX_PAYLOAD + 1
+            })
+        );
+    }
+
+    #[test]
+    fn order_and_capacity() {
+        let mut queue: UplinkQueue<3> = UplinkQueue::new(RetryPolicy::default());
+        let now = Instant::now();
+        assert_eq!(queue.next_due(now), Err(None));
+
+        let low = Uplink::new(1, b"low")
+            .unwrap()
+            .priority(UplinkPriority::Low);
+        let normal = Uplink::new(2, b"normal").unwrap();
+        let high = Uplink::new(3, b"high")
+            .unwrap()
+            .priority(UplinkPriority::High);
+        assert_eq!(queue.push(low, None), Ok(0));
+        assert_eq!(queue.push(normal.clone(), None), Ok(1));
+        assert_eq!(queue.push(normal.clone(), None), Ok(2));
+        assert!(queue.is_full());
+        assert_eq!(queue.push(high.clone(), None), Err(high.clone()));
+
+        // Oldest first within a priority
+        assert_eq!(queue.next_due(now), Ok(1));
+        assert_eq!(queue.cancel(1), Some(normal));
+        assert_eq!(queue.cancel(1), None);
+        assert_eq!(queue.push(high, None), Ok(3));
+        assert_eq!(queue.next_due(now), Ok(2));
+
+        // Waiting on a retry does not hold up the rest
+        queue.entries[2].not_before = Some(now + Duration::from_secs(10));
+        assert_eq!(queue.next_due(now), Ok(1));
+        queue.entries[0].not_before = Some(now + Duration::from_secs(20));
+        queue.entries[1].not_before = Some(now + Duration::from_secs(30));
+        assert_eq!(queue.next_due(now), Err(Some(Duration::from_secs(10))));
+        assert_eq!(queue.next_due(now + Duration::from_secs(10)), Ok(2));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn flush() {
+        use crate::lora::queue::UplinkId;
+        use crate::lora::responses::LoraSendBytesResponse;
+        use crate::sim::{run_client, Mkl62baSim};
+        use core::cell::RefCell;
+        use std::vec::Vec;
+
+        let completed: RefCell<Vec<(UplinkId, bool)>> = RefCell::new(Vec::new());
+        let on_complete = |id, result: &Result<LoraSendBytesResponse, Error>| {
+            completed.borrow_mut().push((id, result.is_ok()))
+        };
+
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.joined = true);
+        run_client(
+            &sim,
+            |builder| builder.skip_reset(),
+            |mut client, sim| async move {
+                let mut queue: UplinkQueue<4> = UplinkQueue::new(RetryPolicy {
+                    max_attempts: 2,
+                    busy_backoff_ms: 200,
+                });
+                let low = Uplink::new(1, b"low")
+                    .unwrap()
+                    .priority(UplinkPriority::Low);
+                let high = Uplink::new(2, b"high")
+                    .unwrap()
+                    .priority(UplinkPriority::High)
+                    .confirmed();
+                let normal = Uplink::new(3, b"normal").unwrap();
+                assert_eq!(queue.push(low, Some(&on_complete)), Ok(0));
+                assert_eq!(queue.push(high, Some(&on_complete)), Ok(1));
+                assert_eq!(queue.push(normal, Some(&on_complete)), Ok(2));
+
+                // The module is busy for the high priority one, it is retried after the others
+                sim.fail_command("+SENDB", -3);
+                queue.flush(&mut client).await;
+                assert!(queue.is_empty());
+            },
+        );
+        assert_eq!(*completed.borrow(), [(2, true), (0, true), (1, true)]);
+        sim.with_state(|s| {
+            // Only the confirmed uplink switched +CONFIRM, and set it back after either attempt
+            assert!(!s.confirm);
+            let confirm: Vec<&str> = s
+                .commands
+                .iter()
+                .filter(|c| c.starts_with("+CONFIRM=") && *c != "+CONFIRM=?")
+                .map(|c| c.as_str())
+                .collect();
+            assert_eq!(
+                confirm,
+                ["+CONFIRM=ON", "+CONFIRM=OFF", "+CONFIRM=ON", "+CONFIRM=OFF"]
+            );
+            let sends = s.commands.iter().filter(|c| c.starts_with("+SENDB="));
+            assert_eq!(sends.count(), 4);
+            assert_eq!(
+                s.uplinks,
+                [
+                    (3, Vec::from(&b"normal"[..])),
+                    (1, Vec::from(&b"low"[..])),
+                    (2, Vec::from(&b"high"[..]))
+                ]
+            );
+        });
+    }
+}
@@ -1,8 +1,12 @@
+#[cfg(feature = "unverified-commands")]
+use super::types::{Channel, ChannelMask};
 use super::types::{LoraClass, LoraJoinMode as LoraJoinModeVal, LoraRegion as LoraRegionVal};
-use crate::lora::types::LoraJoiningStatus;
+use crate::error::ParseError;
+use crate::lora::types::{LoraJoiningStatus, LORA_MAX_PAYLOAD};
 use atat::serde_at::HexStr;
 use atat_derive::AtatResp;
-use heapless::String;
+use core::str::FromStr;
+use heapless::{String, Vec};
 use heapless_bytes::Bytes;
 /// Lora Join Mode
 #[derive(Debug, Clone, AtatResp, PartialEq)]
@@ -38,6 +42,24 @@ pub struct AppKeyGet {
     pub app_key: HexStr<u128>,
 }
 
+/// DevAddr Get
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct DevAddrGet {
+    pub dev_addr: HexStr<u32>,
+}
+
+/// AppSKey Get
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct AppSKeyGet {
+    pub app_skey: HexStr<u128>,
+}
+
+/// NwkSKey Get
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct NwkSKeyGet {
+    pub nwk_skey: HexStr<u128>,
+}
+
 /// Region
 #[derive(Debug, Clone, AtatResp, PartialEq)]
 pub struct LoraRegionGet {
@@ -80,30 +102,31 @@ pub struct LoraMaxTxLength {
     pub max: u16,
 }
 
-/// Send bytes response, unprocessed. Needs to change : to , in order for AtAt to work
+/// Send bytes response, unprocessed. Fields are separated by :
 #[derive(Debug, Clone, AtatResp, PartialEq)]
 pub struct LoraSendBytesResponseUnprocessed {
     pub val: Bytes<1044>,
 }
 
 /// Parsed send bytes response
-#[derive(Debug, Clone, AtatResp, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LoraSendBytesResponse {
     pub retransmission_times: u8,
     pub port: u8,
-    pub data: HexStr<[u8; 256]>,
+    pub data: Vec<u8, LORA_MAX_PAYLOAD>,
 }
 
-impl From<LoraSendBytesResponseUnprocessed> for LoraSendBytesResponse {
-    fn from(value: LoraSendBytesResponseUnprocessed) -> Self {
-        let mut val = value.val;
-        for b in val.iter_mut() {
-            if *b == b':' {
-                *b = b',';
-            }
-        }
-        let val = core::str::from_utf8(val.as_slice()).unwrap();
-        serde_at::from_str(val).unwrap()
+impl TryFrom<LoraSendBytesResponseUnprocessed> for LoraSendBytesResponse {
+    type Error = ParseError;
+
+    fn try_from(value: LoraSendBytesResponseUnprocessed) -> Result<Self, Self::Error> {
+        let val = core::str::from_utf8(value.val.as_slice()).map_err(|_| ParseError::NotUtf8)?;
+        let mut fields = val.splitn(3, ':');
+        Ok(Self {
+            retransmission_times: parse_field(fields.next())?,
+            port: parse_field(fields.next())?,
+            data: decode_hex(fields.next().ok_or(ParseError::InvalidFormat)?)?,
+        })
     }
 }
 
@@ -113,64 +136,193 @@ pub struct LoraReceivedBytesResponseRaw {
     pub value: Bytes<1060>,
 }
 
-/// Parsed send bytes response
-#[derive(Debug, Clone, AtatResp, PartialEq)]
+/// Parsed received ACK
+#[derive(Debug, Clone, PartialEq)]
 pub struct LoraReceivedBytesAckResponse {
     pub rssi: i32,
     pub snr: f32,
     pub ack: String<6>,
 }
 
-/// Parsed send bytes response
-#[derive(Debug, Clone, AtatResp, PartialEq)]
-pub struct LoraReceivedBytesDataResponse {
+/// Parsed received bytes, holding up to N bytes of payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoraReceivedBytesDataResponse<const N: usize = LORA_MAX_PAYLOAD> {
     pub rssi: i32,
     pub snr: f32,
     pub port: u8,
     pub length: u16,
-    pub data: HexStr<[u8; 256]>,
+    pub data: Vec<u8, N>,
 }
 
-/// Data rate (DR) set response
+/// Data rate (DR) get / set response
 #[derive(Debug, Clone, AtatResp, PartialEq)]
 pub struct DrSetResponse {
     pub data_rate: u8,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum LoraReceivedBytes {
+pub enum LoraReceivedBytes<const N: usize = LORA_MAX_PAYLOAD> {
     None,
     Ack(LoraReceivedBytesAckResponse),
-    Data(LoraReceivedBytesDataResponse),
-}
-
-impl From<LoraReceivedBytesResponseRaw> for LoraReceivedBytes {
-    fn from(value: LoraReceivedBytesResponseRaw) -> Self {
-        if value.value.is_empty() {
-            Self::None
-        } else {
-            let mut val = value.value;
-            for b in val.iter_mut() {
-                if *b == b':' {
-                    *b = b',';
-                }
-            }
-            let val = core::str::from_utf8(val.as_slice()).unwrap();
-            if val.ends_with(",ACK") {
-                Self::Ack(serde_at::from_str(val).unwrap())
-            } else {
-                Self::Data(serde_at::from_str(val).unwrap())
+    Data(LoraReceivedBytesDataResponse<N>),
+}
+
+impl<const N: usize> TryFrom<&str> for LoraReceivedBytes<N> {
+    type Error = ParseError;
+
+    /// Parses `rssi:snr:ACK` or `rssi:snr:port:length:data`
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Ok(Self::None);
+        }
+        let mut fields = value.splitn(5, ':');
+        let rssi = parse_field(fields.next())?;
+        let snr = parse_field(fields.next())?;
+        let port = fields.next().ok_or(ParseError::InvalidFormat)?;
+        if port == "ACK" {
+            if fields.next().is_some() {
+                return Err(ParseError::InvalidFormat);
             }
+            return Ok(Self::Ack(LoraReceivedBytesAckResponse {
+                rssi,
+                snr,
+                ack: port.into(),
+            }));
         }
+        let port = parse_field(Some(port))?;
+        let length = parse_field(fields.next())?;
+        let data: Vec<u8, N> = decode_hex(fields.next().ok_or(ParseError::InvalidFormat)?)?;
+        if data.len() != length as usize {
+            return Err(ParseError::InvalidFormat);
+        }
+        Ok(Self::Data(LoraReceivedBytesDataResponse {
+            rssi,
+            snr,
+            port,
+            length,
+            data,
+        }))
+    }
+}
+
+impl<const N: usize> TryFrom<LoraReceivedBytesResponseRaw> for LoraReceivedBytes<N> {
+    type Error = ParseError;
+
+    fn try_from(value: LoraReceivedBytesResponseRaw) -> Result<Self, Self::Error> {
+        core::str::from_utf8(value.value.as_slice())
+            .map_err(|_| ParseError::NotUtf8)?
+            .try_into()
     }
 }
 
 impl LoraReceivedBytesResponseRaw {
-    pub fn processed(self) -> LoraReceivedBytes {
-        self.into()
+    pub fn processed(self) -> Result<LoraReceivedBytes, ParseError> {
+        self.try_into()
+    }
+}
+
+fn parse_field<T: FromStr>(field: Option<&str>) -> Result<T, ParseError> {
+    field
+        .ok_or(ParseError::InvalidFormat)?
+        .parse()
+        .map_err(|_| ParseError::InvalidFormat)
+}
+
+fn decode_hex<const N: usize>(hex: &str) -> Result<Vec<u8, N>, ParseError> {
+    if hex.len() % 2 != 0 {
+        return Err(ParseError::InvalidFormat);
+    }
+    let mut data = Vec::new();
+    for i in (0..hex.len()).step_by(2) {
+        let byte = hex
+            .get(i..i + 2)
+            .and_then(|b| u8::from_str_radix(b, 16).ok())
+            .ok_or(ParseError::InvalidFormat)?;
+        data.push(byte).map_err(|_| ParseError::Overflow)?;
+    }
+    Ok(data)
+}
+
+/// TX power index get / set response
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct TxPowerResponse {
+    pub tx_power: u8,
+}
+
+/// RX1 delay get / set response, in seconds
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct Rx1DelayResponse {
+    pub delay: u8,
+}
+
+/// RX2 frequency get / set response, in Hz
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct Rx2FrequencyResponse {
+    pub frequency: u32,
+}
+
+/// Join accept delay get / set response, in seconds
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct JoinAcceptDelayResponse {
+    pub delay: u8,
+}
+
+/// Channel mask get / set response, `FFFF:0000:0000:0000:00FF`
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct ChannelMaskResponse {
+    pub mask: String<24>,
+}
+
+#[cfg(feature = "unverified-commands")]
+impl ChannelMaskResponse {
+    pub fn mask(&self) -> Result<ChannelMask, ParseError> {
+        self.mask.parse()
     }
 }
 
+/// Channel add / enable / info response, `index:frequency:min_dr:max_dr:ON`. A channel that is
+/// not defined has frequency 0.
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct ChannelResponse {
+    pub channel: String<32>,
+}
+
+#[cfg(feature = "unverified-commands")]
+impl ChannelResponse {
+    /// Index and channel, `None` if it is not defined
+    pub fn channel(&self) -> Result<(u8, Option<Channel>), ParseError> {
+        let mut fields = self.channel.split(':');
+        let index = parse_field(fields.next())?;
+        let channel = Channel {
+            frequency: parse_field(fields.next())?,
+            min_dr: parse_field(fields.next())?,
+            max_dr: parse_field(fields.next())?,
+            enabled: match fields.next() {
+                Some("ON") => true,
+                Some("OFF") => false,
+                _ => return Err(ParseError::InvalidFormat),
+            },
+        };
+        if fields.next().is_some() {
+            return Err(ParseError::InvalidFormat);
+        }
+        Ok((index, (channel.frequency != 0).then_some(channel)))
+    }
+}
+
+/// Channel remove response
+#[cfg(feature = "unverified-commands")]
+#[derive(Debug, Clone, AtatResp, PartialEq)]
+pub struct ChannelRemoveResponse {
+    pub index: u8,
+}
+
 /// Uplink frame count response
 #[derive(Debug, Clone, AtatResp, PartialEq)]
 pub struct UplinkFrameCountResponse {
@@ -185,6 +337,7 @@ pub struct DownlinkFrameCountResponse {
 
 #[cfg(test)]
 mod tests {
+    use crate::error::ParseError;
     use crate::lora::responses::{
         LoraClassGet, LoraReceivedBytes, LoraReceivedBytesAckResponse,
         LoraReceivedBytesDataResponse, LoraReceivedBytesResponseRaw, LoraRegionGet,
@@ -192,9 +345,8 @@ mod tests {
     };
     use crate::lora::types::{LoraClass, LoraRegion as LoraRegionVal};
     use core::str::FromStr;
-    use heapless::String;
+    use heapless::{String, Vec};
     use heapless_bytes::Bytes;
-    use serde_at::HexStr;
 
     #[test]
     fn lora_region() {
@@ -205,6 +357,35 @@ mod tests {
         assert_eq!(r, LoraRegionVal::Eu868);
     }
 
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn channel() {
+        use crate::lora::responses::ChannelResponse;
+        use crate::lora::types::Channel;
+
+        let r = ChannelResponse {
+            channel: String::from("3:867100000:0:5:OFF"),
+        };
+        assert_eq!(
+            r.channel(),
+            Ok((
+                3,
+                Some(Channel {
+                    enabled: false,
+                    ..Channel::new(867_100_000, 0, 5)
+                })
+            ))
+        );
+        let r = ChannelResponse {
+            channel: String::from("9:0:0:0:OFF"),
+        };
+        assert_eq!(r.channel(), Ok((9, None)));
+        let r = ChannelResponse {
+            channel: String::from("3:867100000:0:5"),
+        };
+        assert_eq!(r.channel(), Err(ParseError::InvalidFormat));
+    }
+
     #[test]
     fn lora_class() {
         let r = LoraClassGet { class: "C".into() };
@@ -215,26 +396,65 @@ mod tests {
     #[test]
     fn lora_send_bytes_response() {
         let r = LoraSendBytesResponseUnprocessed {
-            val: Bytes::from_slice(b"3:12:ABCDEF").unwrap(),
+            val: Bytes::from_slice(b"3:12:ABCDEF00").unwrap(),
         };
-        let r: LoraSendBytesResponse = r.into();
+        let r: LoraSendBytesResponse = r.try_into().unwrap();
         assert_eq!(r.retransmission_times, 3);
         assert_eq!(r.port, 12);
-        let mut v = [0; 256];
-        v[0] = 0xAB;
-        v[1] = 0xCD;
-        v[2] = 0xEF;
-        assert_eq!(*r.data, v);
+        assert_eq!(r.data.as_slice(), &[0xAB, 0xCD, 0xEF, 0x00]);
+    }
+
+    #[test]
+    fn lora_send_bytes_response_corrupted() {
+        let r = LoraSendBytesResponseUnprocessed {
+            val: Bytes::from_slice(b"3:1\xff:ABCDEF").unwrap(),
+        };
+        let r: Result<LoraSendBytesResponse, _> = r.try_into();
+        assert_eq!(r, Err(ParseError::NotUtf8));
+
+        let r = LoraSendBytesResponseUnprocessed {
+            val: Bytes::from_slice(b"3:x2:ABCDEF").unwrap(),
+        };
+        let r: Result<LoraSendBytesResponse, _> = r.try_into();
+        assert_eq!(r, Err(ParseError::InvalidFormat));
+
+        let r = LoraSendBytesResponseUnprocessed {
+            val: Bytes::from_slice(b"3:12:ABCDE").unwrap(),
+        };
+        let r: Result<LoraSendBytesResponse, _> = r.try_into();
+        assert_eq!(r, Err(ParseError::InvalidFormat));
+    }
+
+    #[test]
+    fn received_bytes_capacity() {
+        let value = Bytes::from_slice(b"-102:-4.0:8:4:31323334").unwrap();
+        let k: Result<LoraReceivedBytes<2>, _> = LoraReceivedBytesResponseRaw { value }.try_into();
+        assert_eq!(k, Err(ParseError::Overflow));
+
+        let value = Bytes::from_slice(b"-102:-4.0:8:5:31323334").unwrap();
+        let k = LoraReceivedBytesResponseRaw { value }.processed();
+        assert_eq!(k, Err(ParseError::InvalidFormat));
+    }
+
+    #[test]
+    fn received_bytes_corrupted() {
+        let value = Bytes::from_slice(b"-104:1:\xfeCK").unwrap();
+        let k = LoraReceivedBytesResponseRaw { value }.processed();
+        assert_eq!(k, Err(ParseError::NotUtf8));
+
+        let value = Bytes::from_slice(b"-10x:1:ACK").unwrap();
+        let k = LoraReceivedBytesResponseRaw { value }.processed();
+        assert_eq!(k, Err(ParseError::InvalidFormat));
     }
 
     #[test]
     fn received_bytes() {
         let value = Bytes::from_slice(b"").unwrap();
-        let k = LoraReceivedBytesResponseRaw { value }.processed();
+        let k = LoraReceivedBytesResponseRaw { value }.processed().unwrap();
         assert_eq!(k, LoraReceivedBytes::None);
 
         let value = Bytes::from_slice(b"-104:1:ACK").unwrap();
-        let k = LoraReceivedBytesResponseRaw { value }.processed();
+        let k = LoraReceivedBytesResponseRaw { value }.processed().unwrap();
         assert_eq!(
             k,
             LoraReceivedBytes::Ack(LoraReceivedBytesAckResponse {
@@ -244,25 +464,9 @@ mod tests {
             })
         );
 
-        let value = Bytes::from_slice(b"-102:-4.0:8:8:3132333435363738").unwrap();
-        let k = LoraReceivedBytesResponseRaw { value }.processed();
-        let mut data = [0; 256];
-        data[0] = 0x31;
-        data[1] = 0x32;
-        data[2] = 0x33;
-        data[3] = 0x34;
-        data[4] = 0x35;
-        data[5] = 0x36;
-        data[6] = 0x37;
-        data[7] = 0x38;
-        let data = HexStr {
-            val: data,
-            add_0x_with_encoding: false,
-            hex_in_caps: true,
-            delimiter_after_nibble_count: 0,
-            delimiter: ' ',
-            skip_last_0_values: false,
-        };
+        let value = Bytes::from_slice(b"-102:-4.0:8:8:3132333435360000").unwrap();
+        let k = LoraReceivedBytesResponseRaw { value }.processed().unwrap();
+        let data = Vec::from_slice(&[0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x00, 0x00]).unwrap();
         assert_eq!(
             k,
             LoraReceivedBytes::Data(LoraReceivedBytesDataResponse {
@@ -0,0 +1,109 @@
+use crate::error::Error;
+use crate::lora::types::LoraRegion;
+
+/// What the client knows about the current uplink size limit.
+///
+/// The module reported +TX_LEN is cached until the region, data rate or ADR changes. With ADR on
+/// the network can move the data rate on any downlink, so the cache is dropped after each send.
+/// Send reads whatever is missing from the module, ADR included, so the regional table always
+/// applies and the cache is only dropped when ADR is known to be on.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TxLimits {
+    pub(crate) region: Option<LoraRegion>,
+    pub(crate) data_rate: Option<u8>,
+    pub(crate) adr: Option<bool>,
+    pub(crate) max_tx_len: Option<u16>,
+}
+
+impl TxLimits {
+    pub(crate) fn region_changed(&mut self, region: LoraRegion) {
+        self.region = Some(region);
+        self.max_tx_len = None;
+    }
+
+    pub(crate) fn data_rate_changed(&mut self, data_rate: u8) {
+        self.data_rate = Some(data_rate);
+        self.max_tx_len = None;
+    }
+
+    pub(crate) fn adr_changed(&mut self, adr: bool) {
+        self.adr = Some(adr);
+        if adr {
+            self.data_rate = None;
+        }
+        self.max_tx_len = None;
+    }
+
+    pub(crate) fn sent(&mut self) {
+        if self.adr == Some(true) {
+            self.data_rate = None;
+            self.max_tx_len = None;
+        }
+    }
+
+    /// Largest payload allowed by both the module and the regional table
+    pub(crate) fn max_payload(&self, module_max: u16) -> u16 {
+        match (&self.region, self.data_rate) {
+            (Some(region), Some(data_rate)) => region
+                .max_payload(data_rate)
+                .map_or(module_max, |max| max.min(module_max)),
+            _ => module_max,
+        }
+    }
+
+    pub(crate) fn check(&self, module_max: u16, len: usize) -> Result<(), Error> {
+        let max = self.max_payload(module_max);
+        if len > max as usize {
+            Err(Error::PayloadTooLarge { max, actual: len })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::lora::tx_limits::TxLimits;
+    use crate::lora::types::LoraRegion;
+
+    #[test]
+    fn regional_limit_applies() {
+        let mut limits = TxLimits::default();
+        assert!(limits.check(242, 200).is_ok());
+        limits.region_changed(LoraRegion::Eu868);
+        limits.adr_changed(false);
+        limits.data_rate_changed(0);
+        assert_eq!(
+            limits.check(242, 52),
+            Err(Error::PayloadTooLarge {
+                max: 51,
+                actual: 52
+            })
+        );
+        assert_eq!(
+            limits.check(20, 21),
+            Err(Error::PayloadTooLarge {
+                max: 20,
+                actual: 21
+            })
+        );
+    }
+
+    #[test]
+    fn cache_invalidation() {
+        let mut limits = TxLimits {
+            max_tx_len: Some(51),
+            ..Default::default()
+        };
+        limits.adr_changed(false);
+        assert_eq!(limits.max_tx_len, None);
+        limits.max_tx_len = Some(51);
+        limits.sent();
+        assert_eq!(limits.max_tx_len, Some(51));
+        limits.adr_changed(true);
+        limits.max_tx_len = Some(51);
+        limits.sent();
+        assert_eq!(limits.max_tx_len, None);
+    }
+}
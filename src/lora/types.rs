@@ -1,7 +1,19 @@
+use crate::error::{Error, ParseError};
+#[cfg(feature = "unverified-commands")]
+use crate::lora::commands::{ChannelMaskSet, TxPowerSet};
 use crate::lora::commands::{LoraClassSet, LoraRegionSet};
+#[cfg(feature = "unverified-commands")]
+use core::fmt::Write;
+use core::ops::RangeInclusive;
 use core::str::FromStr;
 use heapless::String;
 
+/// Largest application payload LoRaWAN allows in any region / data rate
+pub const LORA_MAX_PAYLOAD: usize = 242;
+
+/// Valid RX1 and join accept delays in seconds
+pub const RX_DELAY_RANGE: RangeInclusive<u8> = 1..=15;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum LoraJoinMode {
     Otaa,
@@ -62,6 +74,289 @@ impl LoraRegion {
             region: self.into(),
         }
     }
+
+    /// Maximum application payload (N) for an uplink at the given data rate, from the LoRaWAN
+    /// regional parameters RP002-1.0.3 (not repeater compatible, no dwell time limit). `None`
+    /// if the data rate is not defined for the region.
+    pub fn max_payload(&self, data_rate: u8) -> Option<u16> {
+        let table: &[u16] = match self {
+            LoraRegion::Eu868 | LoraRegion::Ru864 => &[51, 51, 51, 115, 242, 242, 242, 242],
+            LoraRegion::In865 => &[51, 51, 51, 115, 242, 242, 0, 242],
+            LoraRegion::Us915 => &[11, 53, 125, 242, 242],
+            LoraRegion::Au915 => &[51, 51, 51, 115, 242, 242, 242],
+            LoraRegion::As923 => &[51, 51, 51, 115, 242, 242, 242, 242],
+            LoraRegion::Unknown => &[],
+        };
+        match table.get(data_rate as usize) {
+            Some(0) | None => None,
+            Some(max) => Some(*max),
+        }
+    }
+
+    /// Frequency band of the region in Hz
+    pub fn frequency_range(&self) -> Option<RangeInclusive<u32>> {
+        match self {
+            LoraRegion::Eu868 => Some(863_000_000..=870_000_000),
+            LoraRegion::In865 => Some(865_000_000..=867_000_000),
+            LoraRegion::Ru864 => Some(864_000_000..=870_000_000),
+            LoraRegion::Us915 => Some(902_000_000..=928_000_000),
+            LoraRegion::Au915 | LoraRegion::As923 => Some(915_000_000..=928_000_000),
+            LoraRegion::Unknown => None,
+        }
+    }
+
+    /// Whether the data rate is defined for downlinks in the region, e.g. for RX2
+    pub fn is_downlink_data_rate(&self, data_rate: u8) -> bool {
+        match self {
+            LoraRegion::Eu868 | LoraRegion::Ru864 | LoraRegion::As923 => data_rate <= 7,
+            LoraRegion::In865 => data_rate <= 7 && data_rate != 6,
+            LoraRegion::Us915 | LoraRegion::Au915 => (8..=13).contains(&data_rate),
+            LoraRegion::Unknown => false,
+        }
+    }
+
+    /// US915 and AU915 have a fixed channel plan that is narrowed down with a [ChannelMask]
+    pub fn has_fixed_channels(&self) -> bool {
+        matches!(self, LoraRegion::Us915 | LoraRegion::Au915)
+    }
+
+    /// EU868, IN865, RU864 and AS923 have channels that are defined one by one, see
+    /// [ChannelPlan]
+    pub fn has_dynamic_channels(&self) -> bool {
+        !self.has_fixed_channels() && *self != LoraRegion::Unknown
+    }
+
+    /// Default maximum EIRP in dBm from the LoRaWAN regional parameters
+    pub fn max_eirp(&self) -> Option<i8> {
+        match self {
+            LoraRegion::Eu868 | LoraRegion::Ru864 | LoraRegion::As923 => Some(16),
+            LoraRegion::In865 | LoraRegion::Us915 | LoraRegion::Au915 => Some(30),
+            LoraRegion::Unknown => None,
+        }
+    }
+
+    /// Highest TX power index defined for the region, i.e. the lowest power
+    pub fn max_tx_power(&self) -> Option<TxPower> {
+        match self {
+            LoraRegion::Eu868 | LoraRegion::Ru864 | LoraRegion::As923 => Some(TxPower(7)),
+            LoraRegion::In865 => Some(TxPower(10)),
+            LoraRegion::Us915 | LoraRegion::Au915 => Some(TxPower(14)),
+            LoraRegion::Unknown => None,
+        }
+    }
+}
+
+/// Transmit power as a LoRaWAN TX power index. Index 0 is the maximum EIRP of the region and
+/// every step lowers it by 2 dB, so the range depends on the [LoraRegion].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TxPower(u8);
+
+impl TxPower {
+    /// Maximum EIRP, in any region
+    pub const MAX: TxPower = TxPower(0);
+
+    pub const fn from_index(index: u8) -> Self {
+        Self(index)
+    }
+
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+    /// Highest power that does not go over `dbm` EIRP in the region, or `None` if even the
+    /// lowest power of the region is above `dbm`
+    pub fn from_dbm(dbm: i8, region: &LoraRegion) -> Option<Self> {
+        let steps = (region.max_eirp()? as i16 - dbm as i16).max(0);
+        let power = TxPower(((steps + 1) / 2) as u8);
+        power.is_valid_for(region).then_some(power)
+    }
+
+    /// EIRP in dBm in the region, `None` if the index is not defined for it
+    pub fn dbm(self, region: &LoraRegion) -> Option<i8> {
+        if !self.is_valid_for(region) {
+            return None;
+        }
+        Some(region.max_eirp()? - 2 * self.0 as i8)
+    }
+
+    pub fn is_valid_for(self, region: &LoraRegion) -> bool {
+        matches!(region.max_tx_power(), Some(max) if self <= max)
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    pub fn set_cmd(self) -> TxPowerSet {
+        TxPowerSet { tx_power: self.0 }
+    }
+}
+
+/// Enabled channels of a fixed channel plan region (US915, AU915): 64 125 kHz channels followed
+/// by 8 500 kHz channels. Stored as the LoRaWAN ChMask blocks, bit n of word i is channel
+/// 16 * i + n.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChannelMask([u16; 5]);
+
+impl ChannelMask {
+    /// Number of channels in the plan
+    pub const CHANNELS: u8 = 72;
+
+    /// Every channel of the plan
+    pub const fn all() -> Self {
+        Self([0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0x00FF])
+    }
+
+    /// The 8 125 kHz channels and the 500 kHz channel of sub-band 1 - 8, as used by most
+    /// gateways. Sub-band 2 is channels 8 - 15 and 65.
+    pub fn sub_band(sub_band: u8) -> Option<Self> {
+        if !(1..=8).contains(&sub_band) {
+            return None;
+        }
+        let mut mask = Self::default();
+        for channel in 8 * (sub_band - 1)..8 * sub_band {
+            mask.set(channel, true);
+        }
+        mask.set(64 + sub_band - 1, true);
+        Some(mask)
+    }
+
+    pub const fn from_words(words: [u16; 5]) -> Self {
+        Self(words)
+    }
+
+    pub const fn words(&self) -> [u16; 5] {
+        self.0
+    }
+
+    /// Channels past [CHANNELS](Self::CHANNELS) are ignored
+    pub fn set(&mut self, channel: u8, enabled: bool) {
+        if channel >= Self::CHANNELS {
+            return;
+        }
+        let bit = 1 << (channel % 16);
+        let word = &mut self.0[channel as usize / 16];
+        if enabled {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    pub fn is_enabled(&self, channel: u8) -> bool {
+        channel < Self::CHANNELS && self.0[channel as usize / 16] & (1 << (channel % 16)) != 0
+    }
+
+    pub fn enabled_count(&self) -> u32 {
+        self.0.iter().map(|w| w.count_ones()).sum()
+    }
+
+    /// Only fixed channel plan regions take a mask, and it needs at least one channel
+    pub fn is_valid_for(&self, region: &LoraRegion) -> bool {
+        region.has_fixed_channels() && self.enabled_count() > 0 && self.0[4] & 0xFF00 == 0
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    pub fn set_cmd(self) -> ChannelMaskSet {
+        let mut mask = String::new();
+        for (i, word) in self.0.iter().enumerate() {
+            if i > 0 {
+                // Fits, 5 words of 4 digits and 4 separators
+                mask.push(':').ok();
+            }
+            write!(mask, "{:04X}", word).ok();
+        }
+        ChannelMaskSet { mask }
+    }
+}
+
+impl FromStr for ChannelMask {
+    type Err = ParseError;
+
+    /// `FFFF:0000:0000:0000:00FF`, word 0 first
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut words = [0; 5];
+        let mut parts = value.trim().split(':');
+        for word in words.iter_mut() {
+            let part = parts.next().ok_or(ParseError::InvalidFormat)?;
+            *word = u16::from_str_radix(part, 16).map_err(|_| ParseError::InvalidFormat)?;
+        }
+        if parts.next().is_some() {
+            return Err(ParseError::InvalidFormat);
+        }
+        Ok(Self(words))
+    }
+}
+
+/// A channel of a dynamic channel plan region
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Channel {
+    /// Hz
+    pub frequency: u32,
+    pub min_dr: u8,
+    pub max_dr: u8,
+    pub enabled: bool,
+}
+
+impl Channel {
+    /// An enabled channel
+    pub const fn new(frequency: u32, min_dr: u8, max_dr: u8) -> Self {
+        Self {
+            frequency,
+            min_dr,
+            max_dr,
+            enabled: true,
+        }
+    }
+
+    /// The region has dynamic channels, the frequency is in its band and the data rates are
+    /// defined for it
+    pub fn is_valid_for(&self, region: &LoraRegion) -> bool {
+        region.has_dynamic_channels()
+            && matches!(region.frequency_range(), Some(range) if range.contains(&self.frequency))
+            && self.min_dr <= self.max_dr
+            && region.max_payload(self.max_dr).is_some()
+    }
+}
+
+/// Channels of a dynamic channel plan region (EU868, IN865, RU864, AS923) by index
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ChannelPlan {
+    channels: [Option<Channel>; ChannelPlan::MAX_CHANNELS as usize],
+}
+
+impl ChannelPlan {
+    /// Number of channel slots of the module
+    pub const MAX_CHANNELS: u8 = 16;
+
+    pub fn get(&self, index: u8) -> Option<&Channel> {
+        self.channels.get(index as usize)?.as_ref()
+    }
+
+    /// Define the channel at `index`, [Error::InvalidArgument] past
+    /// [MAX_CHANNELS](Self::MAX_CHANNELS)
+    pub fn set(&mut self, index: u8, channel: Channel) -> Result<(), Error> {
+        let slot = self
+            .channels
+            .get_mut(index as usize)
+            .ok_or(Error::InvalidArgument)?;
+        *slot = Some(channel);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: u8) -> Option<Channel> {
+        self.channels.get_mut(index as usize)?.take()
+    }
+
+    /// Defined channels with their index
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Channel)> {
+        self.channels
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| Some((i as u8, c.as_ref()?)))
+    }
+
+    /// Every channel is valid for the region and at least one is enabled
+    pub fn is_valid_for(&self, region: &LoraRegion) -> bool {
+        self.iter().all(|(_, c)| c.is_valid_for(region)) && self.iter().any(|(_, c)| c.enabled)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -122,3 +417,252 @@ impl From<String<20>> for LoraJoiningStatus {
         }
     }
 }
+
+/// What [send](crate::client::asynch::MokoMkl62BaClient::send) does while the module reported
+/// `NEXT TX after(s)` has not passed yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DutyCyclePolicy {
+    /// Wait until the module allows the next uplink
+    #[default]
+    Wait,
+    /// Fail with [Error::DutyCycleRestricted] right away
+    Reject,
+}
+
+/// Credentials used for an OTAA join
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtaaCredentials {
+    pub dev_eui: u64,
+    pub app_eui: u64,
+    pub app_key: u128,
+}
+
+/// How hard to try joining the network before giving up
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinPolicy {
+    /// Maximum number of +JOINING attempts
+    pub max_attempts: u8,
+    /// How long to wait for a single attempt to report a result on +JOIN_STD
+    pub attempt_timeout_ms: u32,
+    /// Interval between +JOIN_STD polls while an attempt is in progress
+    pub poll_interval_ms: u32,
+    /// Wait after the first failed attempt
+    pub initial_backoff_ms: u32,
+    /// Upper bound for the wait between attempts
+    pub max_backoff_ms: u32,
+    /// Factor the wait is multiplied with after every failed attempt
+    pub backoff_multiplier: u32,
+}
+
+impl Default for JoinPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            attempt_timeout_ms: 30_000,
+            poll_interval_ms: 1_000,
+            initial_backoff_ms: 5_000,
+            max_backoff_ms: 300_000,
+            backoff_multiplier: 2,
+        }
+    }
+}
+
+impl JoinPolicy {
+    /// Backoff to apply after the given number of failed attempts (1 based)
+    pub fn backoff_ms(&self, failed_attempts: u8) -> u32 {
+        let mut backoff = self.initial_backoff_ms;
+        for _ in 1..failed_attempts {
+            backoff = backoff.saturating_mul(self.backoff_multiplier);
+            if backoff >= self.max_backoff_ms {
+                break;
+            }
+        }
+        backoff.min(self.max_backoff_ms)
+    }
+}
+
+/// Result of a join driven by [JoinPolicy]
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinOutcome {
+    /// Joined the network after the given number of attempts
+    Joined { attempts: u8 },
+    /// Every attempt ended with JOIN FAILED
+    JoinFailed { attempts: u8 },
+    /// The last attempt did not report a result before its timeout
+    TimedOut { attempts: u8 },
+    /// The module kept reporting ERROR(-3)
+    Busy,
+    /// The module is in ABP mode, ERROR(-2)
+    InAbpMode,
+}
+
+impl JoinOutcome {
+    pub fn is_joined(&self) -> bool {
+        matches!(self, JoinOutcome::Joined { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::error::ParseError;
+    use crate::lora::types::{Channel, ChannelMask, ChannelPlan, JoinPolicy, LoraRegion, TxPower};
+
+    #[test]
+    fn max_payload() {
+        // N per data rate in RP002-1.0.3, not repeater compatible and without dwell time limit.
+        // 0 for a reserved data rate.
+        let spec: &[(LoraRegion, &[u16])] = &[
+            (LoraRegion::Eu868, &[51, 51, 51, 115, 242, 242, 242, 242]),
+            (LoraRegion::Ru864, &[51, 51, 51, 115, 242, 242, 242, 242]),
+            (LoraRegion::In865, &[51, 51, 51, 115, 242, 242, 0, 242]),
+            (LoraRegion::Us915, &[11, 53, 125, 242, 242]),
+            (LoraRegion::Au915, &[51, 51, 51, 115, 242, 242, 242]),
+            (LoraRegion::As923, &[51, 51, 51, 115, 242, 242, 242, 242]),
+            (LoraRegion::Unknown, &[]),
+        ];
+        for (region, table) in spec {
+            for data_rate in 0..16u8 {
+                assert_eq!(
+                    region.max_payload(data_rate),
+                    table
+                        .get(data_rate as usize)
+                        .copied()
+                        .filter(|max| *max != 0),
+                    "{:?} DR{}",
+                    region,
+                    data_rate
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rx2_limits() {
+        assert!(LoraRegion::Eu868
+            .frequency_range()
+            .unwrap()
+            .contains(&869_525_000));
+        assert!(!LoraRegion::Us915
+            .frequency_range()
+            .unwrap()
+            .contains(&869_525_000));
+        assert_eq!(LoraRegion::Unknown.frequency_range(), None);
+        assert!(LoraRegion::Eu868.is_downlink_data_rate(0));
+        assert!(!LoraRegion::In865.is_downlink_data_rate(6));
+        assert!(!LoraRegion::Us915.is_downlink_data_rate(0));
+        assert!(LoraRegion::Au915.is_downlink_data_rate(8));
+    }
+
+    #[test]
+    fn channel_mask() {
+        let mask = ChannelMask::sub_band(2).unwrap();
+        assert_eq!(mask.words(), [0xFF00, 0, 0, 0, 0x0002]);
+        assert!(mask.is_enabled(8));
+        assert!(mask.is_enabled(15));
+        assert!(mask.is_enabled(65));
+        assert!(!mask.is_enabled(16));
+        assert_eq!(mask.enabled_count(), 9);
+        assert_eq!(
+            ChannelMask::sub_band(8).unwrap().words(),
+            [0, 0, 0, 0xFF00, 0x0080]
+        );
+        assert_eq!(ChannelMask::sub_band(0), None);
+        assert_eq!(ChannelMask::sub_band(9), None);
+        assert_eq!(ChannelMask::all().enabled_count(), 72);
+
+        assert!(mask.is_valid_for(&LoraRegion::Us915));
+        assert!(mask.is_valid_for(&LoraRegion::Au915));
+        assert!(!mask.is_valid_for(&LoraRegion::Eu868));
+        assert!(!ChannelMask::default().is_valid_for(&LoraRegion::Us915));
+        assert!(!ChannelMask::from_words([0, 0, 0, 0, 0x0100]).is_valid_for(&LoraRegion::Us915));
+
+        assert_eq!("FF00:0000:0000:0000:0002".parse(), Ok(mask));
+        assert_eq!(
+            "FF00:0000:0000:0000".parse::<ChannelMask>(),
+            Err(ParseError::InvalidFormat)
+        );
+        assert_eq!(
+            "FF00:0000:0000:0000:0002:0000".parse::<ChannelMask>(),
+            Err(ParseError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn channel_plan() {
+        let channel = Channel::new(867_100_000, 0, 5);
+        assert!(channel.is_valid_for(&LoraRegion::Eu868));
+        assert!(!channel.is_valid_for(&LoraRegion::Us915));
+        assert!(!channel.is_valid_for(&LoraRegion::As923));
+        assert!(!Channel::new(867_100_000, 5, 0).is_valid_for(&LoraRegion::Eu868));
+        assert!(!Channel::new(867_100_000, 0, 8).is_valid_for(&LoraRegion::Eu868));
+
+        let mut plan = ChannelPlan::default();
+        assert!(!plan.is_valid_for(&LoraRegion::Eu868));
+        plan.set(3, channel).unwrap();
+        assert_eq!(plan.set(16, channel), Err(Error::InvalidArgument));
+        assert_eq!(plan.get(3), Some(&channel));
+        assert_eq!(plan.get(16), None);
+        assert!(plan.is_valid_for(&LoraRegion::Eu868));
+        plan.set(
+            4,
+            Channel {
+                enabled: false,
+                ..Channel::new(867_300_000, 0, 5)
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            plan.iter()
+                .map(|(i, _)| i)
+                .collect::<heapless::Vec<u8, 16>>(),
+            [3, 4]
+        );
+        assert_eq!(plan.remove(3), Some(channel));
+        assert!(!plan.is_valid_for(&LoraRegion::Eu868));
+    }
+
+    #[test]
+    fn tx_power() {
+        assert_eq!(TxPower::MAX.dbm(&LoraRegion::Eu868), Some(16));
+        assert_eq!(TxPower::from_index(7).dbm(&LoraRegion::Eu868), Some(2));
+        assert_eq!(TxPower::from_index(8).dbm(&LoraRegion::Eu868), None);
+        assert_eq!(TxPower::from_index(14).dbm(&LoraRegion::Us915), Some(2));
+        assert!(TxPower::from_index(10).is_valid_for(&LoraRegion::In865));
+        assert!(!TxPower::from_index(11).is_valid_for(&LoraRegion::In865));
+        assert!(!TxPower::MAX.is_valid_for(&LoraRegion::Unknown));
+
+        assert_eq!(
+            TxPower::from_dbm(14, &LoraRegion::Eu868),
+            Some(TxPower::from_index(1))
+        );
+        assert_eq!(
+            TxPower::from_dbm(13, &LoraRegion::Eu868),
+            Some(TxPower::from_index(2))
+        );
+        assert_eq!(
+            TxPower::from_dbm(20, &LoraRegion::Eu868),
+            Some(TxPower::MAX)
+        );
+        assert_eq!(TxPower::from_dbm(1, &LoraRegion::Eu868), None);
+        assert_eq!(
+            TxPower::from_dbm(10, &LoraRegion::Au915),
+            Some(TxPower::from_index(10))
+        );
+    }
+
+    #[test]
+    fn join_policy_backoff() {
+        let policy = JoinPolicy {
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 10_000,
+            backoff_multiplier: 2,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff_ms(1), 1_000);
+        assert_eq!(policy.backoff_ms(2), 2_000);
+        assert_eq!(policy.backoff_ms(4), 8_000);
+        assert_eq!(policy.backoff_ms(5), 10_000);
+        assert_eq!(policy.backoff_ms(200), 10_000);
+    }
+}
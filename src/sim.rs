@@ -0,0 +1,1312 @@
+//! # Simulated MKL62BA
+//!
+//! A host side stand-in for the module, so [MokoMkl62BaClient](crate::client::asynch::MokoMkl62BaClient)
+//! can be driven end-to-end without hardware. Only available with the `std` feature.
+//!
+//! [Mkl62baSim::split] hands out a reader and a writer that implement `embedded_io`. Commands
+//! written to the writer are answered on the reader with the module framing: `+CMD: value\r\nOK\r\n`
+//! on success and `ERROR(-n)\r\n` on failure. The simulator keeps the module state (echo, sleep,
+//! region, class, keys, counters, join state) in a [SimState] that tests can inspect and modify
+//! with [Mkl62baSim::with_state].
+//!
+//! ```ignore
+//! let sim = Mkl62baSim::new();
+//! let (rx, tx) = sim.split();
+//! sim.queue_downlink(8, -102, -4.0, b"12");
+//! ```
+
+#[cfg(feature = "unverified-commands")]
+use crate::general::types::BaudRate;
+use crate::general::types::FirmwareVersion;
+#[cfg(feature = "unverified-commands")]
+use crate::lora::types::{Channel, ChannelMask, ChannelPlan, TxPower};
+use crate::lora::types::{LoraClass, LoraJoinMode, LoraRegion, LORA_MAX_PAYLOAD};
+use core::fmt::Write as _;
+use std::collections::VecDeque;
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::vec::Vec;
+
+/// State of the simulated module
+#[derive(Debug, Clone)]
+pub struct SimState {
+    pub echo: bool,
+    pub sleep: bool,
+    #[cfg(feature = "unverified-commands")]
+    pub baud_rate: u32,
+    pub software_version: FirmwareVersion,
+    pub lora_version: u32,
+    pub region: LoraRegion,
+    /// Only used in US915 and AU915
+    #[cfg(feature = "unverified-commands")]
+    pub channel_mask: ChannelMask,
+    /// Only used in EU868, IN865, RU864 and AS923. The default channels of the region can not
+    /// be changed or removed, and two channels can not share a frequency.
+    #[cfg(feature = "unverified-commands")]
+    pub channel_plan: ChannelPlan,
+    pub join_mode: LoraJoinMode,
+    pub class: LoraClass,
+    pub dev_eui: u64,
+    pub app_eui: u64,
+    pub app_key: u128,
+    pub dev_addr: u32,
+    pub app_skey: u128,
+    pub nwk_skey: u128,
+    pub adr: bool,
+    pub data_rate: u8,
+    #[cfg(feature = "unverified-commands")]
+    pub tx_power: u8,
+    #[cfg(feature = "unverified-commands")]
+    pub rx1_delay: u8,
+    #[cfg(feature = "unverified-commands")]
+    pub rx2_frequency: u32,
+    #[cfg(feature = "unverified-commands")]
+    pub rx2_data_rate: u8,
+    #[cfg(feature = "unverified-commands")]
+    pub join_accept_delay1: u8,
+    #[cfg(feature = "unverified-commands")]
+    pub join_accept_delay2: u8,
+    pub confirm: bool,
+    pub auto_join: bool,
+    pub joined: bool,
+    /// Whether the next join attempt is accepted by the network
+    pub join_accept: bool,
+    /// Number of +JOIN_STD=? polls answered with JOINING before the join attempt completes
+    pub join_polls: u8,
+    /// Announce `NEXT TX after(s):n` after every uplink
+    pub next_tx_after: Option<u16>,
+    /// Report the downlink or ACK of an uplink as a `+RECVB:` URC right away, instead of
+    /// keeping it for +RECVB=?
+    pub downlink_urcs: bool,
+    pub uplink_frame_count: u32,
+    pub downlink_frame_count: u32,
+    /// Number of resets, including [Mkl62baSim::boot]
+    pub resets: u32,
+    /// Every uplink sent with +SENDB, as port and payload
+    pub uplinks: Vec<(u8, Vec<u8>)>,
+    /// Every command received, without the `AT` prefix, e.g. `+DR=?`
+    pub commands: Vec<String>,
+    joining: Option<u8>,
+    fail_next: Option<i16>,
+    fail_commands: Vec<(String, i16)>,
+    downlinks: VecDeque<String>,
+    receive: Option<String>,
+    input: Vec<u8>,
+    output: VecDeque<u8>,
+}
+
+impl Default for SimState {
+    fn default() -> Self {
+        Self {
+            echo: true,
+            sleep: false,
+            #[cfg(feature = "unverified-commands")]
+            baud_rate: 9600,
+            software_version: FirmwareVersion {
+                major: 1,
+                minor: 0,
+                patch: 2,
+            },
+            lora_version: 103,
+            region: LoraRegion::Eu868,
+            #[cfg(feature = "unverified-commands")]
+            channel_mask: ChannelMask::all(),
+            #[cfg(feature = "unverified-commands")]
+            channel_plan: default_channels(&LoraRegion::Eu868),
+            join_mode: LoraJoinMode::Otaa,
+            class: LoraClass::ClassA,
+            dev_eui: 0,
+            app_eui: 0,
+            app_key: 0,
+            dev_addr: 0,
+            app_skey: 0,
+            nwk_skey: 0,
+            adr: true,
+            data_rate: 0,
+            #[cfg(feature = "unverified-commands")]
+            tx_power: 0,
+            #[cfg(feature = "unverified-commands")]
+            rx1_delay: 1,
+            #[cfg(feature = "unverified-commands")]
+            rx2_frequency: 869_525_000,
+            #[cfg(feature = "unverified-commands")]
+            rx2_data_rate: 0,
+            #[cfg(feature = "unverified-commands")]
+            join_accept_delay1: 5,
+            #[cfg(feature = "unverified-commands")]
+            join_accept_delay2: 6,
+            confirm: false,
+            auto_join: false,
+            joined: false,
+            join_accept: true,
+            join_polls: 1,
+            next_tx_after: None,
+            downlink_urcs: false,
+            uplink_frame_count: 0,
+            downlink_frame_count: 0,
+            resets: 0,
+            uplinks: Vec::new(),
+            commands: Vec::new(),
+            joining: None,
+            fail_next: None,
+            fail_commands: Vec::new(),
+            downlinks: VecDeque::new(),
+            receive: None,
+            input: Vec::new(),
+            output: VecDeque::new(),
+        }
+    }
+}
+
+/// Simulated MKL62BA, see the [module documentation](self)
+#[derive(Clone, Default)]
+pub struct Mkl62baSim {
+    state: Arc<Mutex<SimState>>,
+}
+
+/// Module to host half of a [Mkl62baSim]
+pub struct SimRx {
+    state: Arc<Mutex<SimState>>,
+}
+
+/// Host to module half of a [Mkl62baSim]
+pub struct SimTx {
+    state: Arc<Mutex<SimState>>,
+}
+
+/// The simulator never fails on I/O, this only exists to satisfy `embedded_io`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimError;
+
+impl embedded_io::Error for SimError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        embedded_io::ErrorKind::Other
+    }
+}
+
+impl Mkl62baSim {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reader and writer to hand to atat
+    pub fn split(&self) -> (SimRx, SimTx) {
+        (
+            SimRx {
+                state: self.state.clone(),
+            },
+            SimTx {
+                state: self.state.clone(),
+            },
+        )
+    }
+
+    /// Look at or change the module state
+    pub fn with_state<R>(&self, f: impl FnOnce(&mut SimState) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    /// Power on the module: turn echo back on, drop the join and emit the boot banner
+    pub fn boot(&self) {
+        self.lock().boot();
+    }
+
+    /// Answer the next command with `ERROR(code)`
+    pub fn fail_next(&self, code: i16) {
+        self.lock().fail_next = Some(code);
+    }
+
+    /// Answer the next `name` command, e.g. `+SENDB`, with `ERROR(code)`
+    pub fn fail_command(&self, name: &str, code: i16) {
+        self.lock().fail_commands.push((name.into(), code));
+    }
+
+    /// Downlink answered on the next +RECVB=? after an uplink, as in class A
+    pub fn queue_downlink(&self, port: u8, rssi: i32, snr: f32, data: &[u8]) {
+        let downlink = downlink(port, rssi, snr, data);
+        self.lock().downlinks.push_back(downlink);
+    }
+
+    /// Unsolicited downlink, as received in class C
+    pub fn inject_downlink(&self, port: u8, rssi: i32, snr: f32, data: &[u8]) {
+        let mut state = self.lock();
+        state.downlink_frame_count += 1;
+        let line = std::format!("+RECVB: {}\r\n", downlink(port, rssi, snr, data));
+        state.emit(&line);
+    }
+
+    /// Unsolicited ACK for a confirmed uplink
+    pub fn inject_ack(&self, rssi: i32, snr: f32) {
+        let mut state = self.lock();
+        state.downlink_frame_count += 1;
+        let line = std::format!("+RECVB: {}\r\n", ack(rssi, snr));
+        state.emit(&line);
+    }
+
+    /// Emit raw bytes as if sent by the module
+    pub fn inject(&self, bytes: &[u8]) {
+        self.lock().output.extend(bytes);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SimState> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// What a command handler of [SimState] made of a command
+enum Handled {
+    /// Not one of its commands
+    No,
+    /// Answer with the current value of the setting
+    Value,
+    /// Already answered
+    Answered,
+}
+
+type Handler = fn(&mut SimState, &str, Option<&str>) -> Result<Handled, i16>;
+
+impl SimState {
+    fn emit(&mut self, s: &str) {
+        self.output.extend(s.as_bytes());
+    }
+
+    fn ok(&mut self, name: &str, value: &str) {
+        let response = std::format!("{}: {}\r\nOK\r\n", name, value);
+        self.emit(&response);
+    }
+
+    fn error(&mut self, code: i16) {
+        let response = std::format!("ERROR({})\r\n", code);
+        self.emit(&response);
+    }
+
+    fn boot(&mut self) {
+        self.resets += 1;
+        self.echo = true;
+        self.sleep = false;
+        self.joined = false;
+        self.joining = None;
+        self.receive = None;
+        let region: heapless::String<10> = self.region.clone().into();
+        let banner = std::format!(
+            "=====================SYSTEM START=====================\r\n\r\n\
+             ===================SOFT VERSION:{}.{}.{}==============\r\n\r\n\
+             ===================LORA VERSION:{}===============\r\n\r\n\
+             ===================LORA REGION:{}==================\r\n",
+            self.software_version.major,
+            self.software_version.minor,
+            self.software_version.patch,
+            self.lora_version,
+            region
+        );
+        self.emit(&banner);
+    }
+
+    fn write(&mut self, buf: &[u8]) {
+        for b in buf {
+            self.input.push(*b);
+            if self.input.ends_with(b"\r\n") {
+                let line = core::mem::take(&mut self.input);
+                let line = String::from_utf8_lossy(&line[..line.len() - 2]).into_owned();
+                self.command(&line);
+            }
+        }
+    }
+
+    fn command(&mut self, line: &str) {
+        if self.sleep {
+            // The first byte wakes the module up, the command itself is lost
+            self.sleep = false;
+            return;
+        }
+        if self.echo {
+            self.emit(line);
+            self.emit("\r\n");
+        }
+        if let Some(code) = self.fail_next.take() {
+            self.error(code);
+            return;
+        }
+        let command = match line.strip_prefix("AT") {
+            Some(command) => command,
+            None => return self.error(-1),
+        };
+        self.commands.push(command.into());
+        if command.is_empty() {
+            return self.ok("+AT", "OK");
+        }
+        let (name, arg) = match command.split_once('=') {
+            Some((name, "?")) => (name, None),
+            Some((name, arg)) => (name, Some(arg)),
+            None => (command, None),
+        };
+        if let Some(i) = self.fail_commands.iter().position(|(n, _)| n == name) {
+            let (_, code) = self.fail_commands.remove(i);
+            return self.error(code);
+        }
+        if let Err(code) = self.handle(name, arg) {
+            self.error(code);
+        }
+    }
+
+    fn handle(&mut self, name: &str, arg: Option<&str>) -> Result<(), i16> {
+        let handlers: &[Handler] = &[
+            Self::general,
+            Self::join,
+            Self::radio,
+            #[cfg(feature = "unverified-commands")]
+            Self::channels,
+            Self::uplink,
+        ];
+        for handler in handlers {
+            match handler(self, name, arg)? {
+                Handled::No => {}
+                Handled::Value => {
+                    let value = self.value(name);
+                    self.ok(name, &value);
+                    return Ok(());
+                }
+                Handled::Answered => return Ok(()),
+            }
+        }
+        Err(-1)
+    }
+
+    /// Echo, sleep, UART, versions and reset
+    fn general(&mut self, name: &str, arg: Option<&str>) -> Result<Handled, i16> {
+        match (name, arg) {
+            ("+ATE", Some(v)) => self.echo = on_off(v)?,
+            ("+ATE", None) => {}
+            ("+SLEEP", Some(v)) => {
+                let sleep = on_off(v)?;
+                self.ok(name, on_off_str(sleep));
+                self.sleep = sleep;
+                return Ok(Handled::Answered);
+            }
+            ("+SLEEP", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+BAUD", Some(v)) => {
+                let baud_rate = v.parse().map_err(|_| -2)?;
+                BaudRate::try_from(baud_rate).map_err(|_| -2)?;
+                self.baud_rate = baud_rate;
+            }
+            #[cfg(feature = "unverified-commands")]
+            ("+BAUD", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+VER", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+LORA_VER", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+MODEL", None) => {}
+            ("+RESET", None) => {
+                self.ok(name, "OK");
+                self.boot();
+                return Ok(Handled::Answered);
+            }
+            _ => return Ok(Handled::No),
+        }
+        Ok(Handled::Value)
+    }
+
+    /// Join mode, keys and joining
+    fn join(&mut self, name: &str, arg: Option<&str>) -> Result<Handled, i16> {
+        match (name, arg) {
+            ("+JOIN_MODE", Some(v)) => {
+                self.join_mode = match v {
+                    "OTAA" => LoraJoinMode::Otaa,
+                    "ABP" => LoraJoinMode::Abp,
+                    _ => return Err(-2),
+                }
+            }
+            ("+JOIN_MODE", None) => {}
+            ("+DEVEUI", Some(v)) => self.dev_eui = parse_hex(v)? as u64,
+            ("+DEVEUI", None) => {}
+            ("+APPEUI", Some(v)) => self.app_eui = parse_hex(v)? as u64,
+            ("+APPEUI", None) => {}
+            ("+APPKEY", Some(v)) => self.app_key = parse_hex(v)?,
+            ("+APPKEY", None) => {}
+            ("+DEVADDR", Some(v)) => self.dev_addr = parse_hex(v)? as u32,
+            ("+DEVADDR", None) => {}
+            ("+APPSKEY", Some(v)) => self.app_skey = parse_hex(v)?,
+            ("+APPSKEY", None) => {}
+            ("+NWKSKEY", Some(v)) => self.nwk_skey = parse_hex(v)?,
+            ("+NWKSKEY", None) => {}
+            ("+JOINING", None) => {
+                if self.join_mode != LoraJoinMode::Otaa {
+                    return Err(-2);
+                }
+                if self.joining.is_some() {
+                    return Err(-3);
+                }
+                self.joined = false;
+                self.joining = Some(self.join_polls);
+            }
+            ("+JOIN_STD", None) => {
+                if let Some(polls) = self.joining {
+                    if polls == 0 {
+                        self.joining = None;
+                        self.joined = self.join_accept;
+                    } else {
+                        self.joining = Some(polls - 1);
+                    }
+                }
+            }
+            ("+AUTO_JOIN", Some(v)) => self.auto_join = on_off(v)?,
+            ("+AUTO_JOIN", None) => {}
+            _ => return Ok(Handled::No),
+        }
+        Ok(Handled::Value)
+    }
+
+    /// Region, class and radio parameters
+    fn radio(&mut self, name: &str, arg: Option<&str>) -> Result<Handled, i16> {
+        match (name, arg) {
+            ("+REGION", Some(v)) => {
+                self.region = v.parse().map_err(|_| -2)?;
+                self.data_rate = 0;
+                #[cfg(feature = "unverified-commands")]
+                self.tx_power = 0;
+                #[cfg(feature = "unverified-commands")]
+                (self.rx2_frequency, self.rx2_data_rate) = rx2_default(&self.region);
+                #[cfg(feature = "unverified-commands")]
+                self.channel_mask = ChannelMask::all();
+                #[cfg(feature = "unverified-commands")]
+                self.channel_plan = default_channels(&self.region);
+                self.joined = false;
+            }
+            ("+REGION", None) => {}
+            ("+CLASS", Some(v)) => {
+                self.class = match v {
+                    "A" => LoraClass::ClassA,
+                    "B" => LoraClass::ClassB,
+                    "C" => LoraClass::ClassC,
+                    _ => return Err(-2),
+                }
+            }
+            ("+CLASS", None) => {}
+            ("+ADR", Some(v)) => self.adr = on_off(v)?,
+            ("+ADR", None) => {}
+            ("+DR", Some(v)) => {
+                let data_rate = v.parse().map_err(|_| -2)?;
+                if self.region.max_payload(data_rate).is_none() {
+                    return Err(-2);
+                }
+                self.data_rate = data_rate;
+            }
+            ("+DR", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+TX_POWER", Some(v)) => {
+                let tx_power = TxPower::from_index(v.parse().map_err(|_| -2)?);
+                if !tx_power.is_valid_for(&self.region) {
+                    return Err(-2);
+                }
+                self.tx_power = tx_power.index();
+            }
+            #[cfg(feature = "unverified-commands")]
+            ("+TX_POWER", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+RX1_DELAY", Some(v)) => self.rx1_delay = parse_delay(v)?,
+            #[cfg(feature = "unverified-commands")]
+            ("+RX1_DELAY", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+RX2_FREQ", Some(v)) => {
+                let frequency = v.parse().map_err(|_| -2)?;
+                match self.region.frequency_range() {
+                    Some(range) if range.contains(&frequency) => self.rx2_frequency = frequency,
+                    _ => return Err(-2),
+                }
+            }
+            #[cfg(feature = "unverified-commands")]
+            ("+RX2_FREQ", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+RX2_DR", Some(v)) => {
+                let data_rate = v.parse().map_err(|_| -2)?;
+                if !self.region.is_downlink_data_rate(data_rate) {
+                    return Err(-2);
+                }
+                self.rx2_data_rate = data_rate;
+            }
+            #[cfg(feature = "unverified-commands")]
+            ("+RX2_DR", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+JOIN_DELAY1", Some(v)) => self.join_accept_delay1 = parse_delay(v)?,
+            #[cfg(feature = "unverified-commands")]
+            ("+JOIN_DELAY1", None) => {}
+            #[cfg(feature = "unverified-commands")]
+            ("+JOIN_DELAY2", Some(v)) => self.join_accept_delay2 = parse_delay(v)?,
+            #[cfg(feature = "unverified-commands")]
+            ("+JOIN_DELAY2", None) => {}
+            _ => return Ok(Handled::No),
+        }
+        Ok(Handled::Value)
+    }
+
+    /// Channel mask and channel plan
+    #[cfg(feature = "unverified-commands")]
+    fn channels(&mut self, name: &str, arg: Option<&str>) -> Result<Handled, i16> {
+        match (name, arg) {
+            ("+CH_MASK", Some(v)) => {
+                let mask: ChannelMask = v.parse().map_err(|_| -2)?;
+                if !mask.is_valid_for(&self.region) {
+                    return Err(-2);
+                }
+                self.channel_mask = mask;
+            }
+            ("+CH_MASK", None) if !self.region.has_fixed_channels() => return Err(-2),
+            ("+CH_MASK", None) => {}
+            ("+CH_ADD", Some(v)) => {
+                let mut fields = v.split(':');
+                let index = self.custom_channel(parse_field(fields.next())?)?;
+                let channel = Channel::new(
+                    parse_field(fields.next())?,
+                    parse_field(fields.next())?,
+                    parse_field(fields.next())?,
+                );
+                if fields.next().is_some() || !channel.is_valid_for(&self.region) {
+                    return Err(-2);
+                }
+                let mut others = self.channel_plan.iter().filter(|(i, _)| *i != index);
+                if others.any(|(_, c)| c.frequency == channel.frequency) {
+                    return Err(-2);
+                }
+                self.channel_plan.set(index, channel).map_err(|_| -2)?;
+                let channel = self.channel_info(index);
+                self.ok(name, &channel);
+                return Ok(Handled::Answered);
+            }
+            ("+CH_RM", Some(v)) => {
+                let index = self.custom_channel(v.parse().map_err(|_| -2)?)?;
+                self.channel_plan.remove(index);
+                self.ok(name, v);
+                return Ok(Handled::Answered);
+            }
+            ("+CH_EN", Some(v)) => {
+                let (index, enabled) = v.split_once(':').ok_or(-2)?;
+                let index = index.parse().map_err(|_| -2)?;
+                let mut channel = *self.channel_plan.get(index).ok_or(-2)?;
+                channel.enabled = on_off(enabled)?;
+                self.channel_plan.set(index, channel).map_err(|_| -2)?;
+                let channel = self.channel_info(index);
+                self.ok(name, &channel);
+                return Ok(Handled::Answered);
+            }
+            ("+CH_INFO", Some(v)) => {
+                if !self.region.has_dynamic_channels() {
+                    return Err(-2);
+                }
+                let index = v.parse().map_err(|_| -2)?;
+                if index >= ChannelPlan::MAX_CHANNELS {
+                    return Err(-2);
+                }
+                let channel = self.channel_info(index);
+                self.ok(name, &channel);
+                return Ok(Handled::Answered);
+            }
+            _ => return Ok(Handled::No),
+        }
+        Ok(Handled::Value)
+    }
+
+    /// Uplinks, downlinks and frame counters
+    fn uplink(&mut self, name: &str, arg: Option<&str>) -> Result<Handled, i16> {
+        match (name, arg) {
+            ("+TX_LEN", None) => {}
+            ("+CONFIRM", Some(v)) => self.confirm = on_off(v)?,
+            ("+CONFIRM", None) => {}
+            ("+SENDB", Some(v)) => {
+                self.send(v)?;
+                return Ok(Handled::Answered);
+            }
+            ("+RECVB", None) => {
+                let receive = self.receive.take().unwrap_or_default();
+                self.ok(name, &receive);
+                return Ok(Handled::Answered);
+            }
+            ("+UP_CNT", None) => {}
+            ("+DOWN_CNT", None) => {}
+            _ => return Ok(Handled::No),
+        }
+        Ok(Handled::Value)
+    }
+
+    /// Current value of a setting, as the module reports it
+    fn value(&self, name: &str) -> String {
+        match name {
+            "+ATE" => on_off_str(self.echo).into(),
+            "+SLEEP" => on_off_str(self.sleep).into(),
+            #[cfg(feature = "unverified-commands")]
+            "+BAUD" => self.baud_rate.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+VER" => std::format!(
+                "{}.{}.{}",
+                self.software_version.major,
+                self.software_version.minor,
+                self.software_version.patch
+            ),
+            #[cfg(feature = "unverified-commands")]
+            "+LORA_VER" => self.lora_version.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+MODEL" => "MKL62BA".into(),
+            "+JOIN_MODE" => match self.join_mode {
+                LoraJoinMode::Abp => "ABP".into(),
+                _ => "OTAA".into(),
+            },
+            "+DEVEUI" => hex(&self.dev_eui.to_be_bytes()),
+            "+APPEUI" => hex(&self.app_eui.to_be_bytes()),
+            "+APPKEY" => hex(&self.app_key.to_be_bytes()),
+            "+DEVADDR" => hex(&self.dev_addr.to_be_bytes()),
+            "+APPSKEY" => hex(&self.app_skey.to_be_bytes()),
+            "+NWKSKEY" => hex(&self.nwk_skey.to_be_bytes()),
+            "+REGION" => heapless::String::<10>::from(self.region.clone()).to_string(),
+            "+CLASS" => heapless::String::<2>::from(self.class.clone()).to_string(),
+            "+JOINING" => "JOINING".into(),
+            "+JOIN_STD" => match (self.joining, self.joined) {
+                (Some(_), _) => "JOINING".into(),
+                (None, true) => "JOINED".into(),
+                (None, false) => "JOIN FAILED".into(),
+            },
+            "+AUTO_JOIN" => on_off_str(self.auto_join).into(),
+            "+ADR" => on_off_str(self.adr).into(),
+            "+DR" => self.data_rate.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+TX_POWER" => self.tx_power.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+RX1_DELAY" => self.rx1_delay.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+RX2_FREQ" => self.rx2_frequency.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+RX2_DR" => self.rx2_data_rate.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+JOIN_DELAY1" => self.join_accept_delay1.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+JOIN_DELAY2" => self.join_accept_delay2.to_string(),
+            #[cfg(feature = "unverified-commands")]
+            "+CH_MASK" => self.channel_mask.set_cmd().mask.to_string(),
+            "+TX_LEN" => self.max_tx_len().to_string(),
+            "+CONFIRM" => on_off_str(self.confirm).into(),
+            "+UP_CNT" => self.uplink_frame_count.to_string(),
+            "+DOWN_CNT" => self.downlink_frame_count.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// A channel that is not one of the region defaults
+    #[cfg(feature = "unverified-commands")]
+    fn custom_channel(&self, index: u8) -> Result<u8, i16> {
+        let defaults = default_channels(&self.region).iter().count() as u8;
+        if !self.region.has_dynamic_channels() || index < defaults {
+            return Err(-2);
+        }
+        Ok(index)
+    }
+
+    /// `index:frequency:min_dr:max_dr:ON`, frequency 0 if the channel is not defined
+    #[cfg(feature = "unverified-commands")]
+    fn channel_info(&self, index: u8) -> String {
+        let channel = self.channel_plan.get(index).copied().unwrap_or(Channel {
+            enabled: false,
+            ..Channel::new(0, 0, 0)
+        });
+        std::format!(
+            "{}:{}:{}:{}:{}",
+            index,
+            channel.frequency,
+            channel.min_dr,
+            channel.max_dr,
+            on_off_str(channel.enabled)
+        )
+    }
+
+    fn max_tx_len(&self) -> u16 {
+        self.region.max_payload(self.data_rate).unwrap_or(0)
+    }
+
+    /// `retransmissions:port:hex`
+    fn send(&mut self, value: &str) -> Result<(), i16> {
+        let mut fields = value.splitn(3, ':');
+        let _retransmissions: u8 = parse_field(fields.next())?;
+        let port: u8 = parse_field(fields.next())?;
+        let data = fields.next().ok_or(-2)?;
+        if data.len() % 2 != 0 {
+            return Err(-2);
+        }
+        let data = (0..data.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&data[i..i + 2], 16).map_err(|_| -2))
+            .collect::<Result<Vec<u8>, i16>>()?;
+        if !self.joined {
+            return Err(-5);
+        }
+        if data.len() > self.max_tx_len() as usize || data.len() > LORA_MAX_PAYLOAD {
+            return Err(-2);
+        }
+        self.ok("+SENDB", value);
+        self.uplink_frame_count += 1;
+        self.uplinks.push((port, data));
+        self.receive = match self.downlinks.pop_front() {
+            Some(downlink) => Some(downlink),
+            None if self.confirm => Some(ack(-90, 7.0)),
+            None => None,
+        };
+        if self.receive.is_some() {
+            self.downlink_frame_count += 1;
+        }
+        if let Some(receive) = self.receive.as_ref().filter(|_| self.downlink_urcs) {
+            let line = std::format!("+RECVB: {}\r\n", receive);
+            self.receive = None;
+            self.emit(&line);
+        }
+        if let Some(seconds) = self.next_tx_after {
+            let line = std::format!("NEXT TX after(s):{}\r\n", seconds);
+            self.emit(&line);
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(self.output.len());
+        for (b, o) in buf.iter_mut().zip(self.output.drain(..len)) {
+            *b = o;
+        }
+        len
+    }
+}
+
+fn on_off(value: &str) -> Result<bool, i16> {
+    match value {
+        "ON" => Ok(true),
+        "OFF" => Ok(false),
+        _ => Err(-2),
+    }
+}
+
+fn on_off_str(on: bool) -> &'static str {
+    if on {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+fn parse_field<T: core::str::FromStr>(field: Option<&str>) -> Result<T, i16> {
+    field.ok_or(-2)?.parse().map_err(|_| -2)
+}
+
+/// RX1 and join accept delays, 1 - 15 s
+#[cfg(feature = "unverified-commands")]
+fn parse_delay(value: &str) -> Result<u8, i16> {
+    match value.parse() {
+        Ok(delay @ 1..=15) => Ok(delay),
+        _ => Err(-2),
+    }
+}
+
+/// RX2 frequency and data rate the module falls back to when the region changes
+#[cfg(feature = "unverified-commands")]
+fn rx2_default(region: &LoraRegion) -> (u32, u8) {
+    match region {
+        LoraRegion::Eu868 | LoraRegion::Unknown => (869_525_000, 0),
+        LoraRegion::In865 => (866_550_000, 2),
+        LoraRegion::Ru864 => (869_100_000, 0),
+        LoraRegion::Us915 | LoraRegion::Au915 => (923_300_000, 8),
+        LoraRegion::As923 => (923_200_000, 2),
+    }
+}
+
+/// Channels the module defines when the region changes
+#[cfg(feature = "unverified-commands")]
+fn default_channels(region: &LoraRegion) -> ChannelPlan {
+    let (frequencies, max_dr): (&[u32], u8) = match region {
+        LoraRegion::Eu868 => (&[868_100_000, 868_300_000, 868_500_000], 5),
+        LoraRegion::In865 => (&[865_062_500, 865_402_500, 865_985_000], 5),
+        LoraRegion::Ru864 => (&[868_900_000, 869_100_000], 5),
+        LoraRegion::As923 => (&[923_200_000, 923_400_000], 5),
+        _ => (&[], 0),
+    };
+    let mut plan = ChannelPlan::default();
+    for (index, frequency) in frequencies.iter().enumerate() {
+        plan.set(index as u8, Channel::new(*frequency, 0, max_dr))
+            .unwrap();
+    }
+    plan
+}
+
+/// `30:32:35:38`, as taken and given by the key and EUI commands
+fn hex(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            s.push(':');
+        }
+        let _ = write!(s, "{:02X}", b);
+    }
+    s
+}
+
+fn parse_hex(value: &str) -> Result<u128, i16> {
+    let digits: String = value.chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || digits.len() > 32 {
+        return Err(-2);
+    }
+    u128::from_str_radix(&digits, 16).map_err(|_| -2)
+}
+
+/// `rssi:snr:port:length:data`
+fn downlink(port: u8, rssi: i32, snr: f32, data: &[u8]) -> String {
+    let mut s = std::format!("{}:{:.1}:{}:{}:", rssi, snr, port, data.len());
+    for b in data {
+        let _ = write!(s, "{:02X}", b);
+    }
+    s
+}
+
+/// `rssi:snr:ACK`
+fn ack(rssi: i32, snr: f32) -> String {
+    std::format!("{}:{:.1}:ACK", rssi, snr)
+}
+
+impl embedded_io::Io for SimRx {
+    type Error = SimError;
+}
+
+impl embedded_io::Io for SimTx {
+    type Error = SimError;
+}
+
+impl embedded_io::blocking::Read for SimRx {
+    /// Returns 0 when the module has nothing to say
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.state.lock().unwrap().read(buf))
+    }
+}
+
+impl embedded_io::blocking::Write for SimTx {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.state.lock().unwrap().write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_io::asynch::Read for SimRx {
+    /// Waits until the module has something to say
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        loop {
+            let len = self.state.lock().unwrap().read(buf);
+            if len > 0 || buf.is_empty() {
+                return Ok(len);
+            }
+            YieldNow(false).await;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_io::asynch::Write for SimTx {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.state.lock().unwrap().write(buf);
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Gives other futures, e.g. the one writing commands, a chance to run
+#[cfg(feature = "async")]
+struct YieldNow(bool);
+
+#[cfg(feature = "async")]
+impl core::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<()> {
+        if self.0 {
+            core::task::Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            core::task::Poll::Pending
+        }
+    }
+}
+
+/// Client the tests drive a [Mkl62baSim] with, see [run_client]
+#[cfg(all(test, feature = "async"))]
+pub(crate) type SimClient = crate::client::asynch::MokoMkl62BaClient<'static, SimTx, 1044>;
+
+#[cfg(all(test, feature = "async"))]
+pub(crate) type SimClientBuilder =
+    crate::client::asynch::MokoMkl62BaClientBuilder<'static, SimTx, 1044>;
+
+/// Build a client for `sim` and run `test` with it, while the ingress reads what the sim answers.
+/// `setup` adjusts the builder, which has the URC source set already. `test` gets a handle to
+/// the same sim.
+#[cfg(all(test, feature = "async"))]
+pub(crate) fn run_client<S, T, F>(sim: &Mkl62baSim, setup: S, test: T)
+where
+    S: FnOnce(SimClientBuilder) -> SimClientBuilder,
+    T: FnOnce(SimClient, Mkl62baSim) -> F,
+    F: core::future::Future<Output = ()>,
+{
+    use crate::client::asynch::MokoMkl62BaClient;
+    use crate::digester::{DigesterState, MokoDigester};
+    use crate::urc::URCMessages;
+    use atat::{AtatIngress, Buffers};
+    use embassy_futures::block_on;
+    use embassy_futures::select::{select, Either};
+    use std::boxed::Box;
+
+    let (mut rx, tx) = sim.split();
+    let buffers: &Buffers<URCMessages, 1044, 4, 1> = Box::leak(Box::new(Buffers::new()));
+    let digester: &DigesterState = Box::leak(Box::new(DigesterState::new()));
+    let (mut ingress, client) =
+        buffers.split(tx, MokoDigester::new(digester), atat::Config::default());
+    let urc_subscription = Box::leak(Box::new(buffers.urc_channel.subscribe().unwrap()));
+
+    let sim = sim.clone();
+    let run = async move {
+        let builder = MokoMkl62BaClient::builder(client, digester).urc_source(urc_subscription);
+        let client = setup(builder).build().await.unwrap();
+        test(client, sim).await;
+    };
+    let result = block_on(select(ingress.read_from(&mut rx), run));
+    assert!(matches!(result, Either::Second(())));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lora::types::{LoraClass, LoraRegion};
+    use crate::sim::{Mkl62baSim, SimRx, SimTx};
+    use embedded_io::blocking::{Read, Write};
+    use std::string::String;
+
+    fn command(rx: &mut SimRx, tx: &mut SimTx, cmd: &str) -> String {
+        tx.write_all(cmd.as_bytes()).unwrap();
+        let mut buf = [0u8; 1024];
+        let len = rx.read(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..len]).into_owned()
+    }
+
+    #[test]
+    fn echo_and_framing() {
+        let sim = Mkl62baSim::new();
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT\r\n"),
+            "AT\r\n+AT: OK\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+ATE=OFF\r\n"),
+            "AT+ATE=OFF\r\n+ATE: OFF\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+ATE=?\r\n"),
+            "+ATE: OFF\r\nOK\r\n"
+        );
+        assert_eq!(command(&mut rx, &mut tx, "AT+NOPE=?\r\n"), "ERROR(-1)\r\n");
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+ADR=MAYBE\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        sim.fail_next(-3);
+        assert_eq!(command(&mut rx, &mut tx, "AT+ADR=?\r\n"), "ERROR(-3)\r\n");
+        sim.fail_command("+ADR", -3);
+        assert_eq!(command(&mut rx, &mut tx, "AT+DR=?\r\n"), "+DR: 0\r\nOK\r\n");
+        assert_eq!(command(&mut rx, &mut tx, "AT+ADR=?\r\n"), "ERROR(-3)\r\n");
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+ADR=?\r\n"),
+            "+ADR: ON\r\nOK\r\n"
+        );
+    }
+
+    #[test]
+    fn settings() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+DEVEUI=30:32:35:38:6B:37:5F:03\r\n"),
+            "+DEVEUI: 30:32:35:38:6B:37:5F:03\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+REGION=US915\r\n"),
+            "+REGION: US915\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CLASS=C\r\n"),
+            "+CLASS: C\r\nOK\r\n"
+        );
+        assert_eq!(command(&mut rx, &mut tx, "AT+DR=7\r\n"), "ERROR(-2)\r\n");
+        assert_eq!(command(&mut rx, &mut tx, "AT+DR=3\r\n"), "+DR: 3\r\nOK\r\n");
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+TX_LEN=?\r\n"),
+            "+TX_LEN: 242\r\nOK\r\n"
+        );
+        sim.with_state(|s| {
+            assert_eq!(s.dev_eui, 0x3032_3538_6B37_5F03);
+            assert_eq!(s.region, LoraRegion::Us915);
+            assert_eq!(s.class, LoraClass::ClassC);
+        });
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn rx_windows() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+REGION=US915\r\n"),
+            "+REGION: US915\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+RX2_FREQ=?\r\n"),
+            "+RX2_FREQ: 923300000\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+RX2_FREQ=869525000\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+RX2_DR=10\r\n"),
+            "+RX2_DR: 10\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+RX1_DELAY=16\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+JOIN_DELAY2=7\r\n"),
+            "+JOIN_DELAY2: 7\r\nOK\r\n"
+        );
+        sim.with_state(|s| {
+            assert_eq!(s.rx2_data_rate, 10);
+            assert_eq!(s.join_accept_delay2, 7);
+        });
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn channel_mask() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        // Fixed channel plan regions only
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_MASK=?\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+REGION=US915\r\n"),
+            "+REGION: US915\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_MASK=FF00:0000:0000:0000:0002\r\n"),
+            "+CH_MASK: FF00:0000:0000:0000:0002\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_MASK=0000:0000:0000:0000:0000\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        sim.with_state(|s| assert_eq!(s.channel_mask, ChannelMask::sub_band(2).unwrap()));
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn tx_power() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+TX_POWER=8\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+REGION=US915\r\n"),
+            "+REGION: US915\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+TX_POWER=14\r\n"),
+            "+TX_POWER: 14\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+TX_POWER=15\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        sim.with_state(|s| assert_eq!(s.tx_power, 14));
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn versions() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+VER=?\r\n"),
+            "+VER: 1.0.2\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+LORA_VER=?\r\n"),
+            "+LORA_VER: 103\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+MODEL=?\r\n"),
+            "+MODEL: MKL62BA\r\nOK\r\n"
+        );
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn baud_rate() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+BAUD=115200\r\n"),
+            "+BAUD: 115200\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+BAUD=1234\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        sim.with_state(|s| assert_eq!(s.baud_rate, 115200));
+    }
+
+    #[test]
+    fn join_send_receive() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| {
+            s.echo = false;
+            s.next_tx_after = Some(5);
+        });
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+SENDB=0:8:3132\r\n"),
+            "ERROR(-5)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+JOINING\r\n"),
+            "+JOINING: JOINING\r\nOK\r\n"
+        );
+        assert_eq!(command(&mut rx, &mut tx, "AT+JOINING\r\n"), "ERROR(-3)\r\n");
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+JOIN_STD=?\r\n"),
+            "+JOIN_STD: JOINING\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+JOIN_STD=?\r\n"),
+            "+JOIN_STD: JOINED\r\nOK\r\n"
+        );
+
+        sim.queue_downlink(8, -102, -4.0, b"12");
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+SENDB=0:8:3132\r\n"),
+            "+SENDB: 0:8:3132\r\nOK\r\nNEXT TX after(s):5\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+RECVB=?\r\n"),
+            "+RECVB: -102:-4.0:8:2:3132\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+RECVB=?\r\n"),
+            "+RECVB: \r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+UP_CNT=?\r\n"),
+            "+UP_CNT: 1\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+DOWN_CNT=?\r\n"),
+            "+DOWN_CNT: 1\r\nOK\r\n"
+        );
+        sim.with_state(|s| assert_eq!(s.uplinks, [(8, std::vec![0x31, 0x32])]));
+    }
+
+    #[cfg(feature = "unverified-commands")]
+    #[test]
+    fn channels() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_INFO=0\r\n"),
+            "+CH_INFO: 0:868100000:0:5:ON\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_INFO=3\r\n"),
+            "+CH_INFO: 3:0:0:0:OFF\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_ADD=3:867100000:0:5\r\n"),
+            "+CH_ADD: 3:867100000:0:5:ON\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_EN=3:OFF\r\n"),
+            "+CH_EN: 3:867100000:0:5:OFF\r\nOK\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_ADD=1:867100000:0:5\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_ADD=4:915000000:0:5\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_ADD=4:868100000:0:5\r\n"),
+            "ERROR(-2)\r\n"
+        );
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+CH_RM=3\r\n"),
+            "+CH_RM: 3\r\nOK\r\n"
+        );
+        assert_eq!(command(&mut rx, &mut tx, "AT+CH_RM=0\r\n"), "ERROR(-2)\r\n");
+        sim.with_state(|s| assert_eq!(s.channel_plan.iter().count(), 3));
+    }
+
+    #[test]
+    fn reset_and_sleep() {
+        let sim = Mkl62baSim::new();
+        sim.with_state(|s| s.echo = false);
+        let (mut rx, mut tx) = sim.split();
+        let banner = command(&mut rx, &mut tx, "AT+RESET\r\n");
+        assert!(banner.starts_with("+RESET: OK\r\nOK\r\n=====================SYSTEM START"));
+        assert!(banner.ends_with("LORA REGION:EU868==================\r\n"));
+        sim.with_state(|s| {
+            assert_eq!(s.resets, 1);
+            assert!(s.echo);
+            s.echo = false;
+        });
+
+        assert_eq!(
+            command(&mut rx, &mut tx, "AT+SLEEP=ON\r\n"),
+            "+SLEEP: ON\r\nOK\r\n"
+        );
+        assert_eq!(command(&mut rx, &mut tx, "AT\r\n"), "");
+        assert_eq!(command(&mut rx, &mut tx, "AT\r\n"), "+AT: OK\r\nOK\r\n");
+    }
+
+    #[test]
+    fn unsolicited() {
+        let sim = Mkl62baSim::new();
+        let (mut rx, _tx) = sim.split();
+        sim.inject_downlink(3, -90, 6.5, &[0xAB]);
+        sim.inject_ack(-91, 5.0);
+        let mut buf = [0u8; 128];
+        let len = rx.read(&mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            b"+RECVB: -90:6.5:3:1:AB\r\n+RECVB: -91:5.0:ACK\r\n"
+        );
+    }
+}
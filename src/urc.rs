@@ -3,7 +3,8 @@
 //! This is just used internally, but needs to be public for passing [URCMessages] as a generic to
 //! [AtDigester](atat::digest::AtDigester): `AtDigester<URCMessages>`.
 
-use crate::lora::types::LoraRegion;
+use crate::lora::responses::LoraReceivedBytes;
+use crate::lora::types::{LoraRegion, LORA_MAX_PAYLOAD};
 use crate::urc::URCMessages::SystemStart;
 use atat::digest::ParseError;
 #[cfg(feature = "debug")]
@@ -14,12 +15,17 @@ use atat::{
 };
 #[cfg(feature = "debug")]
 use defmt::error;
+use heapless::Vec;
 
 /// URC definitions, needs to passed as generic of [AtDigester](atat::digest::AtDigester): `AtDigester<URCMessages>`
-#[derive(Debug, PartialEq, Eq, Clone)]
+///
+/// Only `PartialEq`, the SNR of downlinks is an `f32`. Match on the variant to compare URCs
+/// where `Eq` is needed.
+#[derive(Debug, PartialEq, Clone)]
 pub enum URCMessages {
     /// Unknown URC message
     Unknown,
+    /// An echoed command was seen, the module has echo on again, e.g. after an unexpected reset
     AteOn,
     AteOff,
     SystemStart,
@@ -27,6 +33,18 @@ pub enum URCMessages {
     LoraVersion(u32),
     LoraRegion(LoraRegion),
     NextTxInSeconds(u16),
+    /// Downlink received after an uplink, or at any time in class C
+    Downlink {
+        port: u8,
+        rssi: i32,
+        snr: f32,
+        data: Vec<u8, LORA_MAX_PAYLOAD>,
+    },
+    /// ACK for a confirmed uplink
+    Ack {
+        rssi: i32,
+        snr: f32,
+    },
 }
 
 impl URCMessages {
@@ -137,6 +155,51 @@ impl URCMessages {
             })?;
         Ok(URCMessages::NextTxInSeconds(seconds))
     }
+
+    pub(crate) fn parse_downlink(buf: &[u8]) -> Result<URCMessages, ParseError> {
+        let value = buf.strip_prefix(b"+RECVB: ").ok_or(ParseError::NoMatch)?;
+        let value = core::str::from_utf8(value).map_err(|_e| {
+            #[cfg(feature = "debug")]
+            error!("Failed to parse downlink string, {:?}", LossyStr(value));
+            ParseError::NoMatch
+        })?;
+        match LoraReceivedBytes::<LORA_MAX_PAYLOAD>::try_from(value) {
+            Ok(LoraReceivedBytes::Data(data)) => Ok(URCMessages::Downlink {
+                port: data.port,
+                rssi: data.rssi,
+                snr: data.snr,
+                data: data.data,
+            }),
+            Ok(LoraReceivedBytes::Ack(ack)) => Ok(URCMessages::Ack {
+                rssi: ack.rssi,
+                snr: ack.snr,
+            }),
+            Ok(LoraReceivedBytes::None) => Err(ParseError::NoMatch),
+            Err(_e) => {
+                #[cfg(feature = "debug")]
+                error!("Failed to parse downlink");
+                Err(ParseError::NoMatch)
+            }
+        }
+    }
+}
+
+/// Source of parsed URCs the client drains to keep track of module state.
+///
+/// Implemented for the atat URC subscription, so `buffers.urc_channel.subscribe()` can be handed
+/// to the client directly.
+pub trait UrcSource {
+    /// Next pending URC, if any. Must not block.
+    fn try_next_urc(&mut self) -> Option<URCMessages>;
+}
+
+#[cfg(feature = "async")]
+impl<'sub, const CAPACITY: usize, const SUBSCRIBERS: usize> UrcSource
+    for atat::UrcSubscription<'sub, URCMessages, CAPACITY, SUBSCRIBERS>
+{
+    fn try_next_urc(&mut self) -> Option<URCMessages> {
+        self.try_next_message_pure()
+    }
 }
 
 impl AtatUrc for URCMessages {
@@ -145,10 +208,12 @@ impl AtatUrc for URCMessages {
     fn parse(resp: &[u8]) -> Option<Self::Response> {
         match resp {
             b"SYSTEM START" => Some(SystemStart),
+            b if b.starts_with(b"AT") => Some(URCMessages::AteOn),
             b if b.starts_with(b"SOFT VERSION:") => URCMessages::parse_software_version(resp).ok(),
             b if b.starts_with(b"LORA VERSION:") => URCMessages::parse_lora_version(resp).ok(),
             b if b.starts_with(b"LORA REGION:") => URCMessages::parse_lora_region(resp).ok(),
             b if b.starts_with(b"NEXT TX after(s):") => URCMessages::parse_next_tx(resp).ok(),
+            b if b.starts_with(b"+RECVB: ") => URCMessages::parse_downlink(resp).ok(),
             _ => None,
         }
     }
@@ -203,7 +268,61 @@ impl Parser for URCMessages {
                 ))),
                 bytes::streaming::tag("\r\n"),
             )),
+            // Unsolicited downlink / ACK
+            sequence::tuple((
+                combinator::success(&b""[..]),
+                combinator::recognize(sequence::tuple((
+                    bytes::streaming::tag("+RECVB: "),
+                    bytes::streaming::take_until("\r\n"),
+                ))),
+                bytes::streaming::tag("\r\n"),
+            )),
         ))(buf)?;
         Ok((data, head.len() + data.len() + tail.len()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::urc::URCMessages;
+    use atat::{AtatUrc, Parser};
+
+    #[test]
+    fn downlink() {
+        let input = b"+RECVB: -102:-4.0:8:3:313200\r\n";
+        let (urc, len) = <URCMessages as Parser>::parse(input).unwrap();
+        assert_eq!(len, input.len());
+        let urc = <URCMessages as AtatUrc>::parse(urc).unwrap();
+        assert_eq!(
+            urc,
+            URCMessages::Downlink {
+                port: 8,
+                rssi: -102,
+                snr: -4.0,
+                data: heapless::Vec::from_slice(&[0x31, 0x32, 0x00]).unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn ack() {
+        let urc = <URCMessages as AtatUrc>::parse(b"+RECVB: -104:1:ACK").unwrap();
+        assert_eq!(
+            urc,
+            URCMessages::Ack {
+                rssi: -104,
+                snr: 1.0
+            }
+        );
+        assert_eq!(<URCMessages as AtatUrc>::parse(b"+RECVB: "), None);
+        assert_eq!(<URCMessages as AtatUrc>::parse(b"+RECVB: 1:2:3"), None);
+    }
+
+    #[test]
+    fn echo() {
+        assert_eq!(
+            <URCMessages as AtatUrc>::parse(b"AT+UP_CNT=?"),
+            Some(URCMessages::AteOn)
+        );
+    }
+}